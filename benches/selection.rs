@@ -0,0 +1,79 @@
+//! Benchmarks for the filtering and selection pipeline over large synthetic
+//! pools, the size a long `batch` range merges into. Each is run at two sizes,
+//! so a change that makes a step quadratic shows up as a 100x jump instead of
+//! a 10x one.
+
+use clap::Parser;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use on_this_day::{filter_events, select_events, Cli, Event};
+use std::hint::black_box;
+
+const SIZES: [usize; 2] = [1_000, 10_000];
+
+/// A feed-like pool of `size` events: years spread over two millennia, every
+/// tenth event a duplicate of an earlier one and every twentieth undated.
+fn pool(size: usize) -> Vec<Event> {
+    let events: Vec<serde_json::Value> = (0..size)
+        .map(|i| {
+            let n = if i % 10 == 9 { i / 2 } else { i };
+            let year = (i % 20 != 19).then_some((n * 7919 % 2000) as i32);
+            serde_json::json!({
+                "text": format!("Synthetic event number {} happens somewhere.", n),
+                "year": year,
+                "pages": [{"content_urls": {"desktop": {"page": format!("https://en.wikipedia.org/wiki/Page_{}", n)}}}],
+            })
+        })
+        .collect();
+    serde_json::from_value(serde_json::Value::Array(events)).expect("synthetic events deserialize")
+}
+
+/// Parses options the way the command line would.
+fn options(flags: &[&str]) -> on_this_day::Args {
+    Cli::parse_from(std::iter::once("on-this-day").chain(flags.iter().copied())).args
+}
+
+fn selection(c: &mut Criterion) {
+    let modes: [(&str, &[&str]); 5] = [
+        ("oldest", &["--oldest", "--count", "5"]),
+        ("newest", &["--newest", "--count", "5"]),
+        ("near_year", &["--near-year", "1500", "--count", "5"]),
+        ("random", &["--count", "5", "--seed", "1"]),
+        ("random_weighted", &["--count", "5", "--seed", "1", "--weight-recency"]),
+    ];
+    let mut group = c.benchmark_group("select_events");
+    let now = chrono::Utc::now();
+    for size in SIZES {
+        let events = pool(size);
+        for (name, flags) in modes {
+            let args = options(flags);
+            group.bench_with_input(BenchmarkId::new(name, size), &events, |b, events| {
+                b.iter(|| select_events(black_box(events), &args, now).0.len())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn filtering(c: &mut Criterion) {
+    let pipelines: [(&str, &[&str]); 2] = [
+        ("dedup", &["--dedup"]),
+        (
+            "all_filters",
+            &["--dedup", "--dedupe-by-page", "--year-parity", "even", "--contains", "event", "--min-text-length", "10", "--sort", "asc"],
+        ),
+    ];
+    let mut group = c.benchmark_group("filter_events");
+    let now = chrono::Utc::now();
+    for size in SIZES {
+        for (name, flags) in pipelines {
+            let args = options(flags);
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter_batched(|| pool(size), |events| filter_events(events, &args, now).map(|events| events.len()), BatchSize::LargeInput)
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, selection, filtering);
+criterion_main!(benches);
@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long a cached API response is considered fresh.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What `cache info` reports about the cache.
+pub struct CacheInfo {
+    /// How many responses are cached.
+    pub entries: usize,
+    /// How many of those are still fresh.
+    pub fresh: usize,
+    /// The total size of the cached responses.
+    pub bytes: u64,
+}
+
+/// One cached response, as listed by `cache info`.
+pub struct CacheEntry {
+    /// The language, event type, month and day the response is for.
+    pub language: String,
+    pub event_type: String,
+    pub month: u32,
+    pub day: u32,
+    /// Whether the response had no events.
+    pub empty: bool,
+    /// How long ago it was cached.
+    pub age: Duration,
+    pub bytes: u64,
+}
+
+/// A simple on-disk cache of raw API responses, one file per request.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Creates a cache rooted at the given directory.
+    pub fn new(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    /// Resolves the cache directory to use.
+    ///
+    /// An explicit `--cache-dir` wins, then `$XDG_CACHE_HOME/on-this-day`
+    /// (honoured on every platform), then the platform's standard cache
+    /// location as reported by the `directories` crate.
+    pub fn resolve_dir(explicit: Option<&Path>) -> Option<PathBuf> {
+        if let Some(dir) = explicit {
+            return Some(dir.to_path_buf());
+        }
+        if let Some(xdg) = std::env::var_os("XDG_CACHE_HOME") {
+            let xdg = PathBuf::from(xdg);
+            // The XDG spec says relative paths are invalid and should be ignored.
+            if xdg.is_absolute() {
+                return Some(xdg.join("on-this-day"));
+            }
+        }
+        directories::ProjectDirs::from("", "", "on-this-day")
+            .map(|dirs| dirs.cache_dir().to_path_buf())
+    }
+
+    /// The directory the cache lives in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The paths of the cached responses. A cache that was never written to is empty.
+    fn entries(&self) -> std::io::Result<Vec<PathBuf>> {
+        let read_dir = match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut paths = Vec::new();
+        for entry in read_dir {
+            let path = entry?.path();
+            // Only touch files the cache itself writes.
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json" || ext == "empty") {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Counts the cached responses and their total size.
+    pub fn info(&self) -> std::io::Result<CacheInfo> {
+        let mut info = CacheInfo { entries: 0, fresh: 0, bytes: 0 };
+        for path in self.entries()? {
+            let metadata = std::fs::metadata(&path)?;
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            info.entries += 1;
+            info.bytes += metadata.len();
+            if age <= CACHE_TTL {
+                info.fresh += 1;
+            }
+        }
+        Ok(info)
+    }
+
+    /// Lists the cached responses, sorted by file name. Files whose names
+    /// don't follow the `{language}-{type}-{MM}-{DD}.json` scheme are left out.
+    /// A response from a non-default `--base-url` lists its source's hash as
+    /// part of the language, e.g. `en@1a2b3c4d`.
+    pub fn list(&self) -> std::io::Result<Vec<CacheEntry>> {
+        let mut paths = self.entries()?;
+        paths.sort();
+        let mut entries = Vec::new();
+        for path in paths {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let (key, empty) = match name.strip_suffix(".empty") {
+                Some(key) => (key, true),
+                None => (name, false),
+            };
+            // Split from the right, since a language code may itself contain a `-`.
+            let mut parts = key.strip_suffix(".json").unwrap_or(key).rsplitn(4, '-');
+            let (Some(day), Some(month), Some(event_type), Some(language)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+                continue;
+            };
+            let (Ok(month), Ok(day)) = (month.parse(), day.parse()) else { continue };
+            let metadata = std::fs::metadata(&path)?;
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            entries.push(CacheEntry {
+                language: language.to_string(),
+                event_type: event_type.to_string(),
+                month,
+                day,
+                empty,
+                age,
+                bytes: metadata.len(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Deletes every cached response, returning how many there were.
+    pub fn clear(&self) -> std::io::Result<usize> {
+        let paths = self.entries()?;
+        for path in &paths {
+            std::fs::remove_file(path)?;
+        }
+        Ok(paths.len())
+    }
+
+    /// Returns the cached body for `key` if it exists and is still fresh.
+    pub fn load(&self, key: &str) -> Option<String> {
+        self.load_fresh(key, CACHE_TTL)
+    }
+
+    /// Returns the body of a response for `key` that had no events, if one was
+    /// cached less than `ttl` ago.
+    pub fn load_empty(&self, key: &str, ttl: Duration) -> Option<String> {
+        self.load_fresh(&empty_file_name(key), ttl)
+    }
+
+    /// Reads the file `name` if it was written less than `ttl` ago.
+    fn load_fresh(&self, name: &str, ttl: Duration) -> Option<String> {
+        let path = self.dir.join(name);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if age > ttl {
+            return None;
+        }
+        std::fs::read_to_string(path).ok()
+    }
+
+    /// Stores a response body under `key`, creating the cache directory if needed.
+    pub fn store(&self, key: &str, body: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(key), body)
+    }
+
+    /// Stores a response body for `key` that had no events. It is kept apart
+    /// from regular responses so it can expire sooner, and so an empty day is
+    /// never mistaken for one that simply hasn't been cached.
+    pub fn store_empty(&self, key: &str, body: &str) -> std::io::Result<()> {
+        self.store(&empty_file_name(key), body)
+    }
+}
+
+/// The file a response without events for `key` is cached in.
+fn empty_file_name(key: &str) -> String {
+    format!("{}.empty", key)
+}
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+
+/// A source of the current time.
+///
+/// Everything that needs "now" goes through a clock rather than calling
+/// `Utc::now()` directly, so date handling can be pinned to a known instant.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The real system clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at a given instant, for `--now`.
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Settings read from the optional `config.toml` file.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub theme: ThemeConfig,
+}
+
+/// The `[theme]` table: an optional preset plus per-element color overrides,
+/// each a standard color name such as `yellow` or `bright-blue`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub preset: Option<String>,
+    pub year: Option<String>,
+    pub text: Option<String>,
+    pub header: Option<String>,
+    pub link: Option<String>,
+}
+
+impl Config {
+    /// Where the config file lives: `$XDG_CONFIG_HOME/on-this-day/config.toml`,
+    /// or the platform's standard config location.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME") {
+            let xdg = PathBuf::from(xdg);
+            if xdg.is_absolute() {
+                return Some(xdg.join("on-this-day").join("config.toml"));
+            }
+        }
+        directories::ProjectDirs::from("", "", "on-this-day")
+            .map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    /// Loads the config file. A missing file is not an error and yields the
+    /// defaults; a file that can't be read or parsed is an error.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|e| format!("Invalid config file {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("Could not read config file {}: {}", path.display(), e)),
+        }
+    }
+}
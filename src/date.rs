@@ -0,0 +1,142 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, FixedOffset, Local, Locale, NaiveDate, Utc};
+
+/// A `--date` argument: a relative keyword, a month and day, or a full date.
+#[derive(Clone, Copy, Debug)]
+pub enum DateArg {
+    Today,
+    Yesterday,
+    Tomorrow,
+    /// `MM-DD`, in the current year.
+    MonthDay(u32, u32),
+    /// `YYYY-MM-DD`.
+    Full(NaiveDate),
+}
+
+/// Parses `today`, `yesterday`, `tomorrow`, `MM-DD` or `YYYY-MM-DD`.
+pub fn parse_date(value: &str) -> Result<DateArg, String> {
+    match value.to_lowercase().as_str() {
+        "today" => return Ok(DateArg::Today),
+        "yesterday" => return Ok(DateArg::Yesterday),
+        "tomorrow" => return Ok(DateArg::Tomorrow),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(DateArg::Full(date));
+    }
+    let (month, day) = value
+        .split_once('-')
+        .and_then(|(m, d)| Some((m.parse().ok()?, d.parse().ok()?)))
+        .ok_or_else(|| "expected MM-DD, YYYY-MM-DD, today, yesterday or tomorrow".to_string())?;
+    // 2024 is a leap year, so 02-29 is accepted.
+    NaiveDate::from_ymd_opt(2024, month, day).ok_or_else(|| format!("'{}' is not a valid day", value))?;
+    Ok(DateArg::MonthDay(month, day))
+}
+
+/// The time zone "today" is worked out in.
+#[derive(Clone, Copy, Debug)]
+pub enum Timezone {
+    Utc,
+    Local,
+    Offset(FixedOffset),
+}
+
+/// Parses `utc`, `local` or a fixed UTC offset like `+05:30`.
+pub fn parse_timezone(value: &str) -> Result<Timezone, String> {
+    match value.to_lowercase().as_str() {
+        "utc" | "z" => Ok(Timezone::Utc),
+        "local" => Ok(Timezone::Local),
+        _ => value
+            .parse::<FixedOffset>()
+            .map(Timezone::Offset)
+            .map_err(|_| "expected utc, local or an offset like +05:30".to_string()),
+    }
+}
+
+/// Works out the date to query from `--date`, defaulting to today, where
+/// "today" is the date at `now` in `timezone`.
+///
+/// An `MM-DD` date takes the current year, except that 02-29 falls back to the
+/// most recent leap year when the current year has no such day.
+pub fn resolve(date: Option<DateArg>, timezone: Timezone, now: DateTime<Utc>) -> NaiveDate {
+    let today = match timezone {
+        Timezone::Utc => now.date_naive(),
+        Timezone::Local => now.with_timezone(&Local).date_naive(),
+        Timezone::Offset(offset) => now.with_timezone(&offset).date_naive(),
+    };
+    match date.unwrap_or(DateArg::Today) {
+        DateArg::Today => today,
+        DateArg::Yesterday => today.pred_opt().unwrap_or(today),
+        DateArg::Tomorrow => today.succ_opt().unwrap_or(today),
+        DateArg::Full(date) => date,
+        DateArg::MonthDay(month, day) => (0..8)
+            .find_map(|back| NaiveDate::from_ymd_opt(today.year() - back, month, day))
+            .unwrap_or(today),
+    }
+}
+
+/// The same month and day `years` before `date`. February 29 becomes February
+/// 28 when the earlier year has no such day, which the flag reports. Fails
+/// when the earlier year is out of the supported range.
+pub fn years_before(date: NaiveDate, years: u32) -> Result<(NaiveDate, bool), String> {
+    let out_of_range = || format!("{} years before {} is out of range", years, date);
+    let year = i32::try_from(years).ok().and_then(|years| date.year().checked_sub(years)).ok_or_else(out_of_range)?;
+    if let Some(earlier) = date.with_year(year) {
+        return Ok((earlier, false));
+    }
+    match (date.month(), date.day()) {
+        (2, 29) => NaiveDate::from_ymd_opt(year, 2, 28).map(|earlier| (earlier, true)).ok_or_else(out_of_range),
+        _ => Err(out_of_range()),
+    }
+}
+
+/// Checks that a `--date-format` string only uses valid strftime specifiers.
+pub fn parse_date_format(value: &str) -> Result<String, String> {
+    if StrftimeItems::new(value).any(|item| item == Item::Error) {
+        return Err("not a valid strftime format, e.g. '%B %-d'".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Formats `date` per `format`, with month and weekday names in the given
+/// Wikipedia language. Languages without a known locale get English names.
+pub fn format_localized(date: NaiveDate, format: &str, language: &str) -> String {
+    let locale = match language {
+        "de" => Locale::de_DE,
+        "fr" => Locale::fr_FR,
+        "es" => Locale::es_ES,
+        "pt" => Locale::pt_PT,
+        "ru" => Locale::ru_RU,
+        "sv" => Locale::sv_SE,
+        "ar" => Locale::ar_SA,
+        "bs" => Locale::bs_BA,
+        _ => Locale::en_US,
+    };
+    date.format_localized(format, locale).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn years_before_keeps_the_month_and_day() {
+        assert_eq!(years_before(ymd(2024, 7, 20), 55), Ok((ymd(1969, 7, 20), false)));
+    }
+
+    #[test]
+    fn years_before_moves_a_leap_day_to_february_28() {
+        assert_eq!(years_before(ymd(2024, 2, 29), 1), Ok((ymd(2023, 2, 28), true)));
+    }
+
+    #[test]
+    fn years_before_rejects_years_out_of_range() {
+        assert!(years_before(ymd(2024, 7, 20), 2_147_485_000).is_err());
+        assert!(years_before(ymd(2024, 7, 20), 1_000_000).is_err());
+        assert!(years_before(ymd(2024, 2, 29), 1_000_000).is_err());
+    }
+}
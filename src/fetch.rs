@@ -0,0 +1,675 @@
+use crate::cache::Cache;
+use crate::{warn, Args, Category, Event, EventType, InputFormat, RetryJitter};
+use std::path::Path;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use reqwest::redirect;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The Wikimedia feed API that serves the "On this day" data.
+pub const DEFAULT_BASE_URL: &str = "https://api.wikimedia.org/feed/v1/wikipedia";
+
+/// The most redirects we follow before giving up, matching reqwest's default.
+const MAX_REDIRECTS: usize = 10;
+
+/// The longest we wait between two attempts at a request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// The raw API response. Entries are optional so that a stray `null` in a
+/// list can be skipped with a warning instead of failing the whole parse,
+/// and a list that doesn't parse at all only costs that one category.
+#[derive(Deserialize, Debug)]
+struct OnThisDayResponse {
+    #[serde(default, deserialize_with = "lenient_list")]
+    selected: Option<List>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    births: Option<List>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    deaths: Option<List>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    holidays: Option<List>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    events: Option<List>,
+}
+
+/// The language `--fallback-on-404` retries with.
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// The lists a response for the `all` feed is expected to have.
+pub const FEED_LISTS: [&str; 5] = ["selected", "births", "deaths", "holidays", "events"];
+
+/// One category's list of events, or why it couldn't be parsed.
+type List = Result<Vec<Option<Event>>, String>;
+
+/// Parses a category's list, keeping a parse error for the caller to report
+/// instead of failing the whole response.
+fn lenient_list<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<List>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.map(|value| serde_json::from_value(value).map_err(|e| e.to_string())))
+}
+
+/// The part of the `featured` feed `--picture` uses.
+#[derive(Deserialize, Debug)]
+struct FeaturedResponse {
+    image: Option<Picture>,
+}
+
+/// The day's featured picture on Wikimedia Commons.
+#[derive(Deserialize, Debug)]
+pub struct Picture {
+    /// The file's title, e.g. `File:Sunset.jpg`.
+    pub title: String,
+    pub description: Option<PictureDescription>,
+    /// The picture's page on Commons.
+    pub file_page: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PictureDescription {
+    pub text: String,
+}
+
+impl OnThisDayResponse {
+    /// Whether the response holds no events at all.
+    fn is_empty(&self) -> bool {
+        [&self.selected, &self.births, &self.deaths, &self.holidays, &self.events]
+            .into_iter()
+            .flatten()
+            .all(|list| list.as_ref().map_or(true, |events| events.iter().all(Option::is_none)))
+    }
+}
+
+/// How long a request to the API took, for `--timings`.
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    /// From sending the request (including any retries) until the response
+    /// headers arrived. reqwest doesn't report DNS and connect times
+    /// separately, so they are part of this.
+    pub ttfb: Duration,
+    /// Until the whole body had been read.
+    pub total: Duration,
+}
+
+/// Fetches days' feeds, going through the cache when one is configured.
+pub struct Fetcher<'a> {
+    pub args: &'a Args,
+    client: reqwest::Client,
+    cache: Option<Cache>,
+    /// The timing of every request `fetch_events` sent to the API.
+    timings: Mutex<Vec<Timing>>,
+    /// Draws the retry jitter; seeded by `--seed`, so retry delays are reproducible.
+    jitter_rng: Mutex<StdRng>,
+}
+
+impl<'a> Fetcher<'a> {
+    /// Builds the HTTP client according to the command-line flags.
+    pub fn new(args: &'a Args, cache: Option<Cache>) -> reqwest::Result<Self> {
+        let redirect_policy = if args.no_redirects {
+            redirect::Policy::none()
+        } else {
+            let verbose = args.verbose;
+            redirect::Policy::custom(move |attempt| {
+                if verbose {
+                    if let Some(from) = attempt.previous().last() {
+                        eprintln!("Redirect: {} -> {}", from, attempt.url());
+                    }
+                }
+                if attempt.previous().len() > MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else {
+                    attempt.follow()
+                }
+            })
+        };
+
+        // We create a client to set a custom User-Agent. Many APIs, including
+        // Wikipedia's, require a User-Agent header to identify the client application.
+        // A 403 Forbidden error is common without one.
+        // See: https://meta.wikimedia.org/wiki/User-Agent_policy
+        let mut builder = reqwest::Client::builder()
+            .user_agent("on-this-day-cli/0.1.0 (A Rust CLI tool to fetch daily historical events)")
+            .redirect(redirect_policy);
+        if let Some(seconds) = args.timeout() {
+            builder = builder.timeout(Duration::from_secs(seconds));
+        }
+        if args.danger_insecure {
+            // Always said out loud, even under --quiet, so it can't go unnoticed.
+            eprintln!("Warning: --danger-insecure is set; TLS certificates are NOT being verified.");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder.build()?;
+
+        let jitter_rng = match args.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Ok(Fetcher { args, client, cache, timings: Mutex::new(Vec::new()), jitter_rng: Mutex::new(jitter_rng) })
+    }
+
+    /// The feed URL for the given language and date.
+    pub fn url(&self, language: &str, month: u32, day: u32) -> String {
+        feed_url(self.args, language, month, day)
+    }
+
+    /// The name of the file the given feed is cached in. Responses from a
+    /// `--base-url` other than the default, or asked for in an
+    /// `--accept-language`, are kept apart by a hash of both after the
+    /// language, so a mirror's data never stands in for the API's.
+    fn cache_key(&self, language: &str, event_type: EventType, month: u32, day: u32) -> String {
+        let event_type_str = format!("{}", event_type).to_lowercase();
+        let mut source = self.args.base_url.trim_end_matches('/').to_string();
+        if let Some(accept_language) = &self.args.accept_language {
+            source = format!("{}|{}", source, accept_language);
+        }
+        let source = if source == DEFAULT_BASE_URL { String::new() } else { format!("@{}", short_hash(&source)) };
+        format!("{}{}-{}-{:02}-{:02}.json", language, source, event_type_str, month, day)
+    }
+
+    /// Sends a GET request, retrying up to `--max-retries` times after errors
+    /// that may be transient: connection failures, timeouts, 429 and 5xx.
+    ///
+    /// The delay before retry `n` is `--retry-backoff` doubled `n - 1` times,
+    /// capped at [`MAX_RETRY_DELAY`]; with full jitter a random delay between
+    /// zero and that is used instead, so that many clients that failed at the
+    /// same moment don't all retry at the same moment too.
+    ///
+    /// The request asks for content in `--accept-language`, or else in
+    /// `language`, the edition being fetched.
+    async fn get(&self, url: &str, language: &str) -> reqwest::Result<reqwest::Response> {
+        let args = self.args;
+        let mut attempt = 0;
+        loop {
+            let accept_language = args.accept_language.as_deref().unwrap_or(language);
+            let result = self.client.get(url).header(reqwest::header::ACCEPT_LANGUAGE, accept_language).send().await;
+            let failure = match &result {
+                Ok(response) if response.status().as_u16() == 429 || response.status().is_server_error() => {
+                    response.status().to_string()
+                }
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => e.to_string(),
+                _ => return result,
+            };
+            if attempt >= args.max_retries() {
+                return result;
+            }
+            attempt += 1;
+
+            let backoff = Duration::from_millis(args.retry_backoff())
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(MAX_RETRY_DELAY);
+            let jitter = if args.no_jitter { RetryJitter::None } else { args.retry_jitter };
+            let delay = match jitter {
+                RetryJitter::None => backoff,
+                RetryJitter::Full => {
+                    let fraction = self.jitter_rng.lock().unwrap_or_else(|e| e.into_inner()).gen::<f64>();
+                    backoff.mul_f64(fraction)
+                }
+            };
+            if args.verbose {
+                eprintln!(
+                    "Retrying in {}ms (attempt {} of {}) after: {}",
+                    delay.as_millis(),
+                    attempt,
+                    args.max_retries(),
+                    failure
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Downloads a day's feed into the cache without parsing or printing it,
+    /// or, under `--separate-requests`, each requested type's feed.
+    pub async fn prefetch(&self, language: &str, month: u32, day: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let feeds = if self.args.separate_feeds() { self.args.event_type.clone() } else { vec![self.args.feed_type()] };
+        futures::future::try_join_all(feeds.into_iter().map(|event_type| self.prefetch_feed(language, event_type, month, day))).await?;
+        Ok(())
+    }
+
+    /// Downloads one feed into the cache. Does nothing without a cache, or
+    /// when a fresh copy is already cached. Like a regular fetch, only a
+    /// successful response that parses is stored, so a bad one can't stand in
+    /// for the day until it expires.
+    async fn prefetch_feed(&self, language: &str, event_type: EventType, month: u32, day: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        let cache_key = self.cache_key(language, event_type, month, day);
+        if cache.load(&cache_key).is_some() || cache.load_empty(&cache_key, self.empty_cache_ttl()).is_some() {
+            return Ok(());
+        }
+
+        let url = typed_feed_url(self.args, language, &format!("{}", event_type).to_lowercase(), month, day);
+        if self.args.verbose {
+            eprintln!("Prefetching {}", url);
+        }
+        let response = self.get(&url, language).await?;
+        // Redirects only get here under --no-redirects, and have no feed to store.
+        if !response.status().is_success() {
+            return Err(format!("the API answered with status {}", response.status()).into());
+        }
+        let body = response.text().await?;
+        let is_empty = serde_json::from_str::<OnThisDayResponse>(&body)?.is_empty();
+        self.store(&cache_key, &body, is_empty);
+        Ok(())
+    }
+
+    /// Fetches the featured picture of the given day, for `--picture`. Returns
+    /// `None` when the day has none. The response is not cached.
+    pub async fn fetch_picture(&self, language: &str, date: chrono::NaiveDate) -> Result<Option<Picture>, Box<dyn std::error::Error>> {
+        let url = format!(
+            "{}/{}/featured/{}",
+            self.args.base_url.trim_end_matches('/'),
+            language,
+            date.format("%Y/%m/%d")
+        );
+        if self.args.verbose {
+            eprintln!("GET {}", url);
+        }
+        let response: FeaturedResponse = self.get(&url, language).await?.error_for_status()?.json().await?;
+        Ok(response.image.map(|mut picture| {
+            if let Some(description) = &mut picture.description {
+                description.text = decode_entities(&description.text);
+            }
+            picture
+        }))
+    }
+
+    /// Fetches the `all` feed for the given day, bypassing the cache, for
+    /// `--verify-endpoint`. Returns its URL and which of [`FEED_LISTS`] the
+    /// response lacks, or has as something other than a list.
+    pub async fn verify_endpoint(
+        &self,
+        language: &str,
+        month: u32,
+        day: u32,
+    ) -> Result<(String, Vec<&'static str>), Box<dyn std::error::Error>> {
+        let url = typed_feed_url(self.args, language, "all", month, day);
+        if self.args.verbose {
+            eprintln!("GET {}", url);
+        }
+        let response = self.get(&url, language).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("{} answered with status {}", url, status).into());
+        }
+        let body: serde_json::Value =
+            response.json().await.map_err(|e| format!("{} didn't answer with JSON ({})", url, e))?;
+        let Some(object) = body.as_object() else {
+            return Err(format!("{} didn't answer with a JSON object", url).into());
+        };
+        let missing = FEED_LISTS.into_iter().filter(|&key| !object.get(key).is_some_and(|list| list.is_array())).collect();
+        Ok((url, missing))
+    }
+
+    /// The timings of the requests made so far, in the order they finished.
+    pub fn timings(&self) -> Vec<Timing> {
+        self.timings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// How long a response without events stays cached.
+    fn empty_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.args.empty_cache_ttl)
+    }
+
+    /// Caches a response body, as an empty result if it had no events.
+    fn store(&self, cache_key: &str, body: &str, is_empty: bool) {
+        if let Some(cache) = &self.cache {
+            // A cache write failure shouldn't stop us from showing the event.
+            let _ = if is_empty { cache.store_empty(cache_key, body) } else { cache.store(cache_key, body) };
+        }
+    }
+
+    /// Fetches and flattens the events for the given language and date, from
+    /// the feed the options ask for.
+    ///
+    /// Returns `Ok(None)` when the API answered with an error status, which has
+    /// already been reported to the user.
+    pub async fn fetch_events(
+        &self,
+        language: &str,
+        month: u32,
+        day: u32,
+    ) -> Result<Option<Vec<Event>>, Box<dyn std::error::Error>> {
+        self.fetch_feed(language, self.args.feed_type(), month, day).await
+    }
+
+    /// Like [`Fetcher::fetch_events`], but from the feed of the given event
+    /// type, for `--separate-requests`.
+    pub async fn fetch_feed(
+        &self,
+        language: &str,
+        event_type: EventType,
+        month: u32,
+        day: u32,
+    ) -> Result<Option<Vec<Event>>, Box<dyn std::error::Error>> {
+        let args = self.args;
+
+        // Construct the API URL for the language, date and event type.
+        let event_type_str = format!("{}", event_type).to_lowercase();
+        let url = typed_feed_url(args, language, &event_type_str, month, day);
+
+        if !args.quiet {
+            // Progress goes to stderr so structured output on stdout stays clean.
+            eprintln!(
+                "Fetching {} event(s) of type '{}' for {:02}/{:02}...",
+                language, event_type_str, month, day
+            );
+        }
+
+        // Look for a fresh cached response before going to the network.
+        let cache_key = self.cache_key(language, event_type, month, day);
+        let cached_body = self.cache.as_ref().and_then(|c| {
+            c.load(&cache_key)
+                .map(|body| (body, "response"))
+                .or_else(|| c.load_empty(&cache_key, self.empty_cache_ttl()).map(|body| (body, "empty response")))
+        });
+
+        let mut fetched = false;
+        let body = match cached_body {
+            Some((body, kind)) => {
+                if args.verbose {
+                    eprintln!("Using cached {} for {}", kind, url);
+                }
+                body
+            }
+            None => {
+                // Make an asynchronous GET request to the Wikipedia API.
+                if args.verbose {
+                    eprintln!("GET {}", url);
+                }
+                let started = Instant::now();
+                let response = self.get(&url, language).await?;
+                let ttfb = started.elapsed();
+                if args.verbose && response.url().as_str() != url {
+                    eprintln!("Resolved to {}", response.url());
+                }
+
+                // Check if the request was successful.
+                let status = response.status();
+                if status.is_redirection() {
+                    // Only reachable with --no-redirects.
+                    let location = response
+                        .headers()
+                        .get(reqwest::header::LOCATION)
+                        .and_then(|l| l.to_str().ok())
+                        .unwrap_or("an unknown location");
+                    eprintln!(
+                        "Error: The API redirected to {} and --no-redirects is set. Status: {}",
+                        location, status
+                    );
+                    return Ok(None);
+                }
+                if status == reqwest::StatusCode::NOT_FOUND && args.fallback_on_404 && language != FALLBACK_LANGUAGE {
+                    warn(
+                        args,
+                        &format!(
+                            "The {} feed for {:02}/{:02} was not found (404); showing the {} one instead.",
+                            language, month, day, FALLBACK_LANGUAGE
+                        ),
+                    );
+                    return Box::pin(self.fetch_feed(FALLBACK_LANGUAGE, event_type, month, day)).await;
+                }
+                if !status.is_success() {
+                    eprintln!(
+                        "Error: Failed to fetch {} events for {:02}/{:02} from Wikipedia API. Status: {}",
+                        language, month, day, status
+                    );
+                    return Ok(None);
+                }
+
+                fetched = true;
+                let body = response.text().await?;
+                let timing = Timing { ttfb, total: started.elapsed() };
+                self.timings.lock().unwrap_or_else(|e| e.into_inner()).push(timing);
+                body
+            }
+        };
+
+        // Deserialize the JSON response into our Rust structs.
+        let api_data: OnThisDayResponse = serde_json::from_str(&body)?;
+        if fetched {
+            // Only cache responses that parsed, and cache empty days for less
+            // long, so newly added events show up soon.
+            self.store(&cache_key, &body, api_data.is_empty());
+        }
+        Ok(Some(self.events_from(api_data, event_type, language, month, day)))
+    }
+
+    /// Reads the events for `--from-file` from a local file instead of the API,
+    /// in the shape `--input-format` names. Bare event lists have no categories,
+    /// so their events all count as general events.
+    pub fn load_file(&self, path: &Path, month: u32, day: u32) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+        let args = self.args;
+        let body = std::fs::read_to_string(path)?;
+        match args.input_format {
+            InputFormat::Api => Ok(self.events_from(serde_json::from_str(&body)?, args.feed_type(), &args.language, month, day)),
+            InputFormat::Events => {
+                let events: Vec<Event> = serde_json::from_str(&body)?;
+                Ok(events
+                    .into_iter()
+                    .map(|event| tag(event, Category::Events, &args.language, month, day))
+                    .collect())
+            }
+        }
+    }
+
+    /// Flattens a parsed response into one list of tagged events, warning
+    /// about missing lists and null entries along the way.
+    fn events_from(&self, api_data: OnThisDayResponse, event_type: EventType, language: &str, month: u32, day: u32) -> Vec<Event> {
+        let args = self.args;
+        // Collect all events from the response into a single vector.
+        // If a specific type was requested, only that list will be populated.
+        // If 'all' was requested, this will combine events from all categories.
+        // Each event is tagged with the category it came from.
+        let mut lists = [
+            (Category::Selected, api_data.selected),
+            (Category::Births, api_data.births),
+            (Category::Deaths, api_data.deaths),
+            (Category::Holidays, api_data.holidays),
+            (Category::Events, api_data.events),
+        ];
+        // Categories named in --merge-order come first, in that order; the
+        // stable sort leaves the rest in the default order after them.
+        let order = &args.merge_order;
+        lists.sort_by_key(|(category, _)| order.iter().position(|c| c == category).unwrap_or(order.len()));
+        if args.shuffle_categories {
+            // With --seed the shuffled order is reproducible.
+            let mut rng = match args.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            lists.shuffle(&mut rng);
+        }
+
+        let mut events_to_process: Vec<Event> = Vec::new();
+        for (category, events) in lists {
+            // A missing list is suspicious whenever it's one we asked for.
+            let expected = event_type.category().is_none_or(|c| c == category) && args.wants(category);
+            let events = match events {
+                Some(Err(e)) => {
+                    if expected {
+                        warn(args, &format!("The '{}' response's '{}' list is malformed ({}); treating it as empty.", language, category, e));
+                    }
+                    continue;
+                }
+                Some(Ok(list)) => Some(list),
+                None => None,
+            };
+            match &events {
+                None if expected => warn(args, &format!("The '{}' response has no '{}' list.", language, category)),
+                Some(list) if list.is_empty() && event_type.category() == Some(category) => {
+                    warn(args, &format!("The '{}' response has an empty '{}' list.", language, category))
+                }
+                _ => {}
+            }
+            // A saved response may hold every list, whatever --event-type asks for.
+            if !expected {
+                continue;
+            }
+
+            let events = events.unwrap_or_default();
+            let total = events.len();
+            let events: Vec<Event> = events.into_iter().flatten().collect();
+            if events.len() < total {
+                warn(args, &format!("Skipped {} null '{}' event(s) in the '{}' response.", total - events.len(), category, language));
+            }
+
+            events_to_process.extend(events.into_iter().map(|event| tag(event, category, language, month, day)));
+        }
+
+        events_to_process
+    }
+}
+
+/// The feed URL the options ask for, for the given language and date.
+pub fn feed_url(args: &Args, language: &str, month: u32, day: u32) -> String {
+    typed_feed_url(args, language, &format!("{}", args.feed_type()).to_lowercase(), month, day)
+}
+
+/// The URL of the feed of the given event type, whatever `--event-type` says.
+fn typed_feed_url(args: &Args, language: &str, event_type: &str, month: u32, day: u32) -> String {
+    format!("{}/{}/onthisday/{}/{:02}/{:02}", args.base_url.trim_end_matches('/'), language, event_type, month, day)
+}
+
+/// The first 8 hex digits of the SHA-256 of `text`.
+fn short_hash(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text).iter().take(4).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Fills in where a parsed event came from, and decodes its text.
+fn tag(mut event: Event, category: Category, language: &str, month: u32, day: u32) -> Event {
+    event.category = category;
+    event.text = decode_entities(&event.text);
+    event.language = language.to_string();
+    event.month = month;
+    event.day = day;
+    event
+}
+
+/// Decodes the HTML entities that turn up in feed text, such as `&amp;` and
+/// `&#39;`, so "AT&amp;T" reads "AT&T". Unknown entities are left as they are.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // The longest entity we decode, "&#x10FFFF;", has 8 characters between `&` and `;`.
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 8)
+            .and_then(|end| Some((decode_entity(&rest[1..=end])?, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character for an entity name without its `&` and `;`, e.g. `amp` or `#39`.
+fn decode_entity(name: &str) -> Option<char> {
+    let code = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        decimal.parse().ok()?
+    } else {
+        return match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            _ => None,
+        };
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Parses options the way the command line would.
+    fn args(flags: &[&str]) -> Args {
+        crate::Cli::parse_from(std::iter::once("on-this-day").chain(flags.iter().copied())).args
+    }
+
+    #[test]
+    fn decodes_named_entities() {
+        assert_eq!(decode_entities("AT&amp;T &lt;b&gt; &quot;hi&quot;"), "AT&T <b> \"hi\"");
+    }
+
+    #[test]
+    fn decodes_numeric_entities() {
+        assert_eq!(decode_entities("Rock &#39;n&#39; roll"), "Rock 'n' roll");
+        assert_eq!(decode_entities("1914&#x2014;1918"), "1914\u{2014}1918");
+    }
+
+    #[test]
+    fn leaves_unknown_entities_alone() {
+        assert_eq!(decode_entities("&bogus; &#xZZ;"), "&bogus; &#xZZ;");
+    }
+
+    #[test]
+    fn leaves_bare_ampersands_alone() {
+        assert_eq!(decode_entities("Marks & Spencer; Tom & Jerry &"), "Marks & Spencer; Tom & Jerry &");
+    }
+
+    #[test]
+    fn a_broken_list_only_costs_its_category() {
+        let response: OnThisDayResponse = serde_json::from_str(
+            r#"{"births": [{"text": "Someone is born.", "year": 1900}], "deaths": "broken", "events": [null, {"text": "Something happens.", "year": 1066}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(response.deaths, Some(Err(_))));
+        assert_eq!(response.births.unwrap().unwrap().len(), 1);
+        let events = response.events.unwrap().unwrap();
+        assert!(events[0].is_none());
+        assert_eq!(events[1].as_ref().and_then(|event| event.year), Some(1066));
+        assert!(response.selected.is_none());
+    }
+
+    #[test]
+    fn string_years_are_parsed_when_numeric() {
+        let response: OnThisDayResponse = serde_json::from_str(
+            r#"{"events": [{"text": "A.", "year": "1969"}, {"text": "B.", "year": "c. 500 BC"}, {"text": "C.", "year": null}]}"#,
+        )
+        .unwrap();
+        let years: Vec<Option<i32>> = response.events.unwrap().unwrap().into_iter().map(|event| event.unwrap().year).collect();
+        assert_eq!(years, [Some(1969), None, None]);
+    }
+
+    #[test]
+    fn cache_keys_keep_other_base_urls_apart() {
+        let default = args(&[]);
+        let mirror = args(&["--base-url", "http://127.0.0.1:8765/"]);
+        let key = |args| Fetcher::new(args, None).unwrap().cache_key("en", EventType::Selected, 7, 4);
+        assert_eq!(key(&default), "en-selected-07-04.json");
+        assert_eq!(key(&mirror), format!("en@{}-selected-07-04.json", short_hash("http://127.0.0.1:8765")));
+    }
+
+    #[test]
+    fn cache_keys_keep_accept_languages_apart() {
+        let key = |args| Fetcher::new(args, None).unwrap().cache_key("en", EventType::Selected, 7, 4);
+        let header = args(&["--accept-language", "en-GB"]);
+        assert_eq!(key(&header), format!("en@{}-selected-07-04.json", short_hash(&format!("{}|en-GB", DEFAULT_BASE_URL))));
+        assert_ne!(key(&header), key(&args(&["--accept-language", "en-US"])));
+    }
+}
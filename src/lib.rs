@@ -0,0 +1,2454 @@
+//! The `on-this-day` command-line tool. It is built as a library, run by the
+//! thin `main.rs`, so benchmarks can reach the filtering and selection logic.
+
+mod cache;
+mod clock;
+mod config;
+mod date;
+mod fetch;
+mod output;
+mod theme;
+
+use cache::Cache;
+use clock::{Clock, FixedClock, SystemClock};
+use config::Config;
+use fetch::Fetcher;
+use chrono::Datelike;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use theme::{Theme, ThemePreset};
+
+/// Represents a historical event with optional year information.
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    text: String,
+    /// Usually a number, but a string such as `"1969"` is accepted too.
+    #[serde(default, deserialize_with = "lenient_year")]
+    year: Option<i32>,
+    /// Wikipedia pages related to the event, most relevant first.
+    #[serde(default)]
+    pages: Vec<Page>,
+    /// Which part of the feed the event came from; filled in after parsing.
+    #[serde(skip)]
+    category: Category,
+    /// The Wikipedia language edition the event came from; filled in after parsing.
+    #[serde(skip)]
+    language: String,
+    /// The month and day whose feed the event came from; filled in after parsing.
+    #[serde(skip)]
+    month: u32,
+    #[serde(skip)]
+    day: u32,
+    /// The text as parsed, kept once tidying for display has rewritten
+    /// `text`, so that the id doesn't depend on how the event is shown.
+    #[serde(skip)]
+    original_text: Option<String>,
+}
+
+/// A year as the API may send it: a number, or a number in a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Year {
+    Number(i32),
+    Text(String),
+}
+
+/// Deserializes an event's year, treating a string that isn't a number as no year.
+fn lenient_year<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+    Ok(match Option::<Year>::deserialize(deserializer)? {
+        Some(Year::Number(year)) => Some(year),
+        Some(Year::Text(text)) => text.trim().parse().ok(),
+        None => None,
+    })
+}
+
+impl Event {
+    /// A short identifier derived from the event's content: the first 12 hex
+    /// digits of the SHA-256 of `language|year|text`, with an empty year for
+    /// undated events and the text as parsed, before any tidying for display.
+    /// The same event always gets the same id across runs and formats.
+    fn id(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let year = self.year.map(|y| y.to_string()).unwrap_or_default();
+        let text = self.original_text.as_deref().unwrap_or(&self.text);
+        let digest = Sha256::digest(format!("{}|{}|{}", self.language, year, text));
+        digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Replaces the text with a version tidied for display, keeping the
+    /// original for the id.
+    fn retext(&mut self, text: String) {
+        let original = std::mem::replace(&mut self.text, text);
+        self.original_text.get_or_insert(original);
+    }
+
+    /// The desktop URL of the event's primary Wikipedia page, if it has one.
+    fn url(&self) -> Option<&str> {
+        self.pages
+            .first()
+            .and_then(|page| page.content_urls.as_ref())
+            .and_then(|urls| urls.desktop.as_ref())
+            .map(|desktop| desktop.page.as_str())
+    }
+
+    /// The opening of the event's primary Wikipedia page, if the feed included it.
+    fn extract(&self) -> Option<&str> {
+        self.pages.first().and_then(|page| page.extract.as_deref())
+    }
+}
+
+/// A Wikipedia page linked from an event. Only the fields we use are kept.
+#[derive(Deserialize, Debug)]
+struct Page {
+    content_urls: Option<ContentUrls>,
+    /// The first paragraph of the page, as plain text.
+    extract: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentUrls {
+    desktop: Option<PageUrls>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PageUrls {
+    page: String,
+}
+
+/// The categories the API groups events into.
+#[derive(Serialize, ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Category {
+    Selected,
+    Births,
+    Deaths,
+    Holidays,
+    #[default]
+    Events,
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// Defines the possible event types the user can request.
+/// Each type can also be given by its first letter, e.g. `-t b`.
+#[derive(ValueEnum, Clone, Debug, Copy)]
+enum EventType {
+    #[value(alias = "a")]
+    All,
+    #[value(alias = "s")]
+    Selected,
+    #[value(alias = "b")]
+    Births,
+    #[value(alias = "d")]
+    Deaths,
+    #[value(alias = "h")]
+    Holidays,
+    #[value(alias = "e")]
+    Events,
+}
+
+impl EventType {
+    /// The response category this type maps to, or `None` for `All`.
+    fn category(self) -> Option<Category> {
+        match self {
+            EventType::All => None,
+            EventType::Selected => Some(Category::Selected),
+            EventType::Births => Some(Category::Births),
+            EventType::Deaths => Some(Category::Deaths),
+            EventType::Holidays => Some(Category::Holidays),
+            EventType::Events => Some(Category::Events),
+        }
+    }
+}
+
+/// Number of warnings reported during the run; `--strict` fails if any were.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Reports a non-fatal oddity in the data, such as a missing category.
+/// Under `--strict` these are reported as errors and fail the run at exit.
+fn warn(args: &Args, message: &str) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    if args.strict {
+        eprintln!("Error: {}", message);
+    } else {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+/// The color theme for text output, resolved on first use.
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Returns the theme to style text output with. Without colors, or with
+/// neither `--theme` nor a `[theme]` in the config file, output is unstyled.
+fn theme(args: &Args) -> &'static Theme {
+    THEME.get_or_init(|| {
+        if !use_color(args) {
+            return Theme::plain();
+        }
+        let config = Config::load().unwrap_or_else(|e| {
+            warn(args, &e);
+            Config::default()
+        });
+        let (theme, problems) = Theme::resolve(args.theme, &config.theme);
+        for problem in problems {
+            warn(args, &problem);
+        }
+        theme
+    })
+}
+
+/// How long `--prefetch-next-day` may delay exiting.
+const PREFETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The furthest `--years-ago` may reach back, well within the years dates can
+/// hold, so that even a `--date` in year 1 stays in range.
+const MAX_YEARS_AGO: i64 = 100_000;
+
+/// The language editions fetched by `--all-languages`: those the Wikimedia
+/// "On this day" feed supports.
+const ALL_LANGUAGES: &[&str] = &["en", "de", "fr", "es", "pt", "ru", "sv", "ar", "bs"];
+
+/// An inclusive span of calendar days for `--range`, e.g. `07-01..07-07`.
+/// A span whose end comes before its start wraps around the new year.
+#[derive(Clone, Copy, Debug)]
+struct DateRange {
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+}
+
+impl DateRange {
+    /// The `(month, day)` of every day in the range, in order.
+    fn days(self) -> impl Iterator<Item = (u32, u32)> {
+        self.start
+            .iter_days()
+            .take_while(move |date| *date <= self.end)
+            .map(|date| (date.month(), date.day()))
+    }
+}
+
+/// The output formats the events can be printed in.
+#[derive(ValueEnum, Clone, Debug, Copy, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable lines (or a table with --table)
+    #[default]
+    Text,
+    /// A single JSON array of flat event objects
+    Json,
+    /// One flat JSON event object per line
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+    /// An HTML fragment with a list of events
+    Html,
+    /// Each event's text and page URL, tab-separated
+    Link,
+}
+
+/// The keys multi-event text output can be grouped into sections by.
+#[derive(ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+enum GroupBy {
+    /// One section per feed category
+    Category,
+    /// One section per decade, e.g. "1960s"
+    Decade,
+    /// One section per century, e.g. "1800s" for 1800-1899
+    Century,
+}
+
+/// What `--template` does with a placeholder the event has no value for.
+#[derive(ValueEnum, Clone, Debug, Copy, Default, PartialEq, Eq)]
+enum TemplateMissing {
+    /// Leave it blank
+    #[default]
+    Empty,
+    /// Leave the whole event out
+    Skip,
+    /// Print the placeholder as written, e.g. `{url}`
+    Literal,
+}
+
+/// What makes two events duplicates of each other.
+#[derive(ValueEnum, Clone, Debug, Copy, Default, PartialEq, Eq)]
+enum DedupKey {
+    /// The same year and exactly the same text
+    #[default]
+    YearText,
+    /// Exactly the same text, whatever the year
+    Text,
+    /// The same text once lowercased and stripped of punctuation and whitespace
+    NormalizedText,
+}
+
+/// Network presets for `--resilience`: a request timeout, a retry count and
+/// a retry backoff that suit a kind of connection.
+#[derive(ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+enum Resilience {
+    /// 5s timeout, no retries: fail quickly on a good connection
+    Fast,
+    /// 15s timeout, 2 retries 500ms apart at first
+    Balanced,
+    /// 60s timeout, 5 retries 2s apart at first: for flaky connections
+    Patient,
+}
+
+impl Resilience {
+    /// The preset's timeout in seconds, retry count and base backoff in milliseconds.
+    fn settings(self) -> (u64, u32, u64) {
+        match self {
+            Resilience::Fast => (5, 0, 200),
+            Resilience::Balanced => (15, 2, 500),
+            Resilience::Patient => (60, 5, 2000),
+        }
+    }
+}
+
+/// A `--assert-count` check: a comparison and the number to compare with.
+#[derive(Clone, Copy, Debug)]
+struct CountAssertion {
+    op: &'static str,
+    n: usize,
+}
+
+impl CountAssertion {
+    fn holds(self, count: usize) -> bool {
+        match self.op {
+            ">=" => count >= self.n,
+            "<=" => count <= self.n,
+            "==" => count == self.n,
+            ">" => count > self.n,
+            "<" => count < self.n,
+            op => unreachable!("parse_count_assertion doesn't accept '{}'", op),
+        }
+    }
+}
+
+/// How retry delays are randomized.
+#[derive(ValueEnum, Clone, Debug, Copy, Default, PartialEq, Eq)]
+enum RetryJitter {
+    /// Wait exactly the backoff delay
+    None,
+    /// Wait a random time between zero and the backoff delay
+    #[default]
+    Full,
+}
+
+/// The shape of a `--from-file` file.
+#[derive(ValueEnum, Clone, Debug, Copy, Default, PartialEq, Eq)]
+enum InputFormat {
+    /// A saved API response, with its per-category lists
+    #[default]
+    Api,
+    /// A bare JSON array of events, like `[{"text": "...", "year": 1969}]`
+    Events,
+}
+
+/// Which years `--year-parity` keeps.
+#[derive(ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+enum Parity {
+    Even,
+    Odd,
+}
+
+/// The orders `--sort` can arrange the pool in.
+#[derive(ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+enum SortOrder {
+    /// Oldest year first, undated events last
+    Asc,
+    /// Newest year first, undated events last
+    Desc,
+    /// Shortest text first
+    Length,
+    /// Longest text first
+    LengthDesc,
+}
+
+/// How to order events that rank equally for `--oldest`, `--newest` or `--near-year`.
+#[derive(ValueEnum, Clone, Debug, Copy, Default)]
+enum TieBreak {
+    /// Prefer the earlier year, then the event listed first
+    #[default]
+    Earliest,
+    /// Prefer the later year, then the event listed last
+    Latest,
+    /// Prefer the most descriptive (longest) text
+    Longest,
+    /// Prefer the most concise (shortest) text
+    Shortest,
+}
+
+impl TieBreak {
+    /// Compares two equally-ranked events, given with their position in the feed.
+    fn compare(self, (a_index, a): (usize, &Event), (b_index, b): (usize, &Event)) -> std::cmp::Ordering {
+        let text_len = |e: &Event| e.text.chars().count();
+        match self {
+            TieBreak::Earliest => a.year.cmp(&b.year).then(a_index.cmp(&b_index)),
+            TieBreak::Latest => b.year.cmp(&a.year).then(b_index.cmp(&a_index)),
+            TieBreak::Longest => text_len(b).cmp(&text_len(a)).then(a_index.cmp(&b_index)),
+            TieBreak::Shortest => text_len(a).cmp(&text_len(b)).then(a_index.cmp(&b_index)),
+        }
+    }
+}
+
+/// Required to convert the enum to a string for the URL.
+impl std::fmt::Display for EventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+/// Defines the command line using clap: a set of subcommands, with `show`'s
+/// options also accepted on their own so that bare `on-this-day` shows today's event.
+#[derive(Parser, Debug)]
+#[command(
+    author = "yu-eric",
+    version = "1.0",
+    about = "Fetches a historical event from Wikipedia's 'On this day' page.",
+    long_about = "A simple command-line tool that fetches a historical event for the current date from the official Wikipedia API. You can choose to get the oldest, newest, or a random event.",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// The subcommands.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show events for one day (the default)
+    Show(Box<Args>),
+    /// Show events for every day in a range
+    Batch(Box<BatchArgs>),
+    /// Inspect or clear the response cache
+    Cache(CacheArgs),
+    /// Print a shell completion script
+    Completions {
+        /// The shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print a man page in roff format
+    Manpage,
+}
+
+/// Options for `batch`: the days to fetch and how to bound the run, on top of
+/// everything `show` accepts.
+#[derive(clap::Args, Debug)]
+struct BatchArgs {
+    /// The span of days to fetch
+    #[arg(long, value_name = "MM-DD..MM-DD", value_parser = parse_range, conflicts_with_all = ["watch", "all_languages", "date", "archive", "from_file", "picture", "with_meta", "menu", "assert_count", "print_date", "cache_info", "clear_cache", "verify_endpoint", "prefetch_next_day", "json_by_category"], help = "Show events for every day from the first to the second date, inclusive.")]
+    range: DateRange,
+
+    /// Overall time budget for the batch
+    #[arg(long, value_name = "SECONDS", help = "Stop fetching after SECONDS in total and show the dates completed so far.")]
+    deadline: Option<u64>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+/// Options for `cache`.
+#[derive(clap::Args, Debug)]
+struct CacheArgs {
+    #[command(subcommand)]
+    action: CacheAction,
+
+    /// Directory where cached API responses are stored
+    #[arg(long, global = true, value_name = "PATH", help = "Directory for cached responses (defaults to $XDG_CACHE_HOME/on-this-day).")]
+    cache_dir: Option<PathBuf>,
+}
+
+/// What to do with the cache.
+#[derive(Subcommand, Debug, Clone, Copy)]
+enum CacheAction {
+    /// Show where the cache is and how much it holds
+    Info,
+    /// Delete every cached response
+    Clear {
+        /// Don't ask for confirmation
+        #[arg(short, long, help = "Delete without asking for confirmation.")]
+        yes: bool,
+    },
+}
+
+/// The options for showing events, shared by `show` and `batch`.
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("year_window").args(["from_year", "to_year"]).multiple(true)))]
+pub struct Args {
+    /// Show the oldest event of the day
+    #[arg(short, long, conflicts_with = "newest", help = "Display the oldest event for today.")]
+    oldest: bool,
+
+    /// Show the newest event of the day
+    #[arg(short, long, conflicts_with = "oldest", help = "Display the newest event for today.")]
+    newest: bool,
+
+    /// Show the N oldest events, in chronological order
+    #[arg(long, value_name = "N", conflicts_with_all = ["top_newest", "oldest", "newest", "pick", "near_year", "count", "seed", "daily", "weight_recency", "cover_categories"], help = "Display the N oldest dated events, oldest first.")]
+    top_oldest: Option<usize>,
+
+    /// Show the N newest events, in chronological order
+    #[arg(long, value_name = "N", conflicts_with_all = ["oldest", "newest", "pick", "near_year", "count", "seed", "daily", "weight_recency", "cover_categories"], help = "Display the N newest dated events, oldest first.")]
+    top_newest: Option<usize>,
+
+    /// Show the event at a given position in the list
+    #[arg(long, value_name = "N", value_parser = parse_pick, allow_negative_numbers = true, conflicts_with_all = ["oldest", "newest", "near_year"], help = "Display the Nth event in feed order (1 is the first, -1 the last).")]
+    pick: Option<i64>,
+
+    /// Show the event closest to a given year
+    #[arg(long, value_name = "YEAR", conflicts_with_all = ["oldest", "newest"], allow_negative_numbers = true, help = "Display the event whose year is closest to YEAR.")]
+    near_year: Option<i32>,
+
+    /// Show the event at a percentile of the dated events' years
+    #[arg(long, value_name = "P", value_parser = clap::value_parser!(u8).range(0..=100), conflicts_with_all = ["oldest", "newest", "pick", "near_year", "top_oldest", "top_newest", "count", "seed", "daily", "weight_recency", "cover_categories"], help = "Display the event at the Pth percentile (0-100) of the dated events sorted by year, e.g. 50 for the median.")]
+    percentile: Option<u8>,
+
+    /// Seed for reproducible random picks
+    #[arg(long, value_name = "N", help = "Seed the random pick so the same seed always selects the same event(s).")]
+    seed: Option<u64>,
+
+    /// Include every category in a random pick
+    #[arg(long, conflicts_with_all = ["pick", "near_year", "oldest", "newest", "weight_recency"], help = "Make random picks include at least one event from each category present before filling the rest of --count.")]
+    cover_categories: bool,
+
+    /// Spread a random pick over several years
+    #[arg(long, value_name = "K", conflicts_with_all = ["pick", "near_year", "percentile", "oldest", "newest", "top_oldest", "top_newest", "weight_recency", "cover_categories"], help = "Make random picks of several events span at least K distinct years, when the pool allows it.")]
+    min_distinct_years: Option<usize>,
+
+    /// Pick the same "event of the day" as everyone else
+    #[arg(long, conflicts_with_all = ["seed", "pick", "near_year", "oldest", "newest"], help = "Seed the random pick with the date, so everyone gets the same event(s) on the same day.")]
+    daily: bool,
+
+    /// Skip the first N picks a seed would produce
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "seed", help = "With --seed, advance past N random selections before picking.")]
+    skip: usize,
+
+    /// Favour recent events in random picks
+    #[arg(long, conflicts_with_all = ["pick", "near_year", "oldest", "newest"], help = "Make random picks favour recent years: the more recent an event, the likelier it is chosen.")]
+    weight_recency: bool,
+
+    /// How to break ties between equally-ranked events
+    #[arg(long, value_enum, default_value_t = TieBreak::Earliest, help = "How to resolve ties for --oldest, --newest and --near-year.")]
+    tie_break: TieBreak,
+
+    /// Filter events by type
+    #[arg(short = 't', long, value_enum, value_delimiter = ',', default_value = "all", help = "Filter by event type; several can be given, comma-separated.")]
+    event_type: Vec<EventType>,
+
+    /// Fetch each requested type's own feed
+    #[arg(long, conflicts_with = "search_all", help = "With several --event-type values, fetch each type's own feed concurrently instead of filtering the whole day's feed, and show each type in its own section.")]
+    separate_requests: bool,
+
+    /// Wikipedia language edition to fetch from
+    #[arg(short = 'l', long, visible_alias = "lang", value_name = "CODE", default_value = "en", help = "Wikipedia language edition to fetch events from (e.g. en, de, fr).")]
+    language: String,
+
+    /// Fall back to English when the language has no feed for the day
+    #[arg(long, conflicts_with = "all_languages", help = "If the --language feed answers 404 Not Found, retry once with the English feed and say so on stderr.")]
+    fallback_on_404: bool,
+
+    /// Fetch every supported language and compare them
+    #[arg(long, conflicts_with_all = ["language", "watch"], help = "Fetch the day's events in all supported languages and group them by language.")]
+    all_languages: bool,
+
+    /// Print each day or language as soon as it's fetched
+    #[arg(long, conflicts_with_all = ["output", "pipe_to", "stats", "no_trailing_newline", "full_html"], help = "In batch mode or with --all-languages, print each day's or language's events as soon as they are fetched instead of all at the end (json stays one document).")]
+    stream: bool,
+
+    /// How many events to show
+    #[arg(short = 'c', long, value_name = "N", default_value_t = 1, help = "Number of events to show (0 shows all of them).")]
+    count: usize,
+
+    /// Output format for the selected events
+    #[arg(short = 'f', long, value_enum, default_value_t = OutputFormat::Text, help = "Output format for the selected events.")]
+    format: OutputFormat,
+
+    /// Include each event's Wikipedia page URL in JSON output
+    #[arg(long, help = "Add a 'url' field (the event's primary page) to json/jsonl output.")]
+    json_include_url: bool,
+
+    /// Tag each event with a content hash in JSON output
+    #[arg(long, help = "Add an 'id' field to json/jsonl output: a stable hash of the event's language, year and text.")]
+    event_id: bool,
+
+    /// Write a self-describing snapshot of the day instead of selected events
+    #[arg(long, conflicts_with_all = ["format", "watch", "all_languages", "stats"], help = "Print a JSON archive of the day: the query, when it was fetched, and every event with its category.")]
+    archive: bool,
+
+    /// Start CSV/TSV output with a UTF-8 byte-order mark
+    #[arg(long, help = "Prepend a UTF-8 byte-order mark to csv/tsv output, so Excel detects the encoding.")]
+    bom: bool,
+
+    /// Drop duplicate events
+    #[arg(long, help = "Remove duplicate events, such as 'selected' events repeated under 'events'.")]
+    dedup: bool,
+
+    /// What counts as a duplicate
+    #[arg(long, value_enum, value_name = "KEY", default_value_t = DedupKey::YearText, help = "How duplicates are detected by --dedup and --all-languages.")]
+    dedup_key: DedupKey,
+
+    /// Drop events about a page an earlier event already covered
+    #[arg(long, help = "Keep only the first event per Wikipedia page (events without a page are always kept).")]
+    dedupe_by_page: bool,
+
+    /// Randomize the order categories are merged in
+    #[arg(long, conflicts_with = "merge_order", help = "Merge categories in a random order (reproducible with --seed).")]
+    shuffle_categories: bool,
+
+    /// Order in which categories are merged into one list
+    #[arg(long, value_enum, value_name = "LIST", value_delimiter = ',', help = "Comma-separated category order for merging, e.g. 'events,births' (unlisted categories follow in the default order).")]
+    merge_order: Vec<Category>,
+
+    /// Categories to leave out
+    #[arg(long, value_enum, value_name = "LIST", value_delimiter = ',', help = "Comma-separated categories to drop, e.g. 'holidays,births'.")]
+    exclude: Vec<Category>,
+
+    /// Leave out holidays
+    #[arg(long, help = "Drop the holidays category (the same as --exclude holidays).")]
+    no_holidays: bool,
+
+    /// Keep only events from even or odd years
+    #[arg(long, value_enum, value_name = "PARITY", help = "Only keep events from even or odd years (undated events are dropped).")]
+    year_parity: Option<Parity>,
+
+    /// Keep only events from one year
+    #[arg(long, value_name = "YEAR", allow_negative_numbers = true, help = "Only keep events from YEAR (undated events are dropped).")]
+    year: Option<i32>,
+
+    /// Keep only events from this year on
+    #[arg(long, value_name = "YEAR", allow_negative_numbers = true, help = "Only keep events from YEAR or later (undated events are dropped).")]
+    from_year: Option<i32>,
+
+    /// Keep only events up to this year
+    #[arg(long, value_name = "YEAR", allow_negative_numbers = true, help = "Only keep events from YEAR or earlier (undated events are dropped).")]
+    to_year: Option<i32>,
+
+    /// Widen --from-year/--to-year to the nearest events if nothing is in range
+    #[arg(long, requires = "year_window", help = "If no event falls between --from-year and --to-year, widen the range just enough to reach the nearest events, with a warning.")]
+    clamp_years: bool,
+
+    /// Keep only events from the year given in --date
+    #[arg(long, conflicts_with = "year", help = "Only keep events from the year of a YYYY-MM-DD --date or of --years-ago, e.g. 2001 for 2001-09-11.")]
+    only_that_year: bool,
+
+    /// Keep only events from one century
+    #[arg(long, value_name = "N", value_parser = parse_century, help = "Only keep events from a century, given as 19 (or 19th) or 1800s; both mean the years 1800 to 1899.")]
+    century: Option<i32>,
+
+    /// Keep only events mentioning some text
+    #[arg(long, value_name = "TEXT", help = "Only keep events whose text contains TEXT (case-insensitive).")]
+    contains: Option<String>,
+
+    /// Fall back to a random event when nothing matches --contains
+    #[arg(long, requires = "contains", conflicts_with_all = ["pick", "near_year", "oldest", "newest"], help = "If no event matches --contains, pick a random event from the whole day instead.")]
+    contains_else_random: bool,
+
+    /// Search every category with --contains
+    #[arg(long, requires = "contains", help = "Fetch the whole day's feed for --contains to search, whatever --event-type says, so matches in other categories aren't missed.")]
+    search_all: bool,
+
+    /// Drop events with very short text
+    #[arg(long, value_name = "N", help = "Drop events whose text is shorter than N characters.")]
+    min_text_length: Option<usize>,
+
+    /// Drop events with very long text
+    #[arg(long, value_name = "N", help = "Drop events whose text is longer than N characters.")]
+    max_text_length: Option<usize>,
+
+    /// Reorder the pool before selecting from it
+    #[arg(long, value_enum, value_name = "ORDER", help = "Sort the day's events by year (asc, desc) or text length (length, length-desc) before selecting.")]
+    sort: Option<SortOrder>,
+
+    /// Keep only the first events of the pool
+    #[arg(long, value_name = "N", help = "Keep only the first N events of the pool (after --sort) to select from.")]
+    head: Option<usize>,
+
+    /// List editorially selected events before the rest
+    #[arg(long, help = "When showing several events, list the editors' 'selected' events first.")]
+    selected_first: bool,
+
+    /// Group events into headed sections
+    #[arg(long, value_enum, value_name = "KEY", num_args = 0..=1, default_missing_value = "category", conflicts_with = "table", help = "Group events into sections by category (the default), decade or century.")]
+    group_by: Option<GroupBy>,
+
+    /// Show each event's Wikipedia link
+    #[arg(long, help = "Print each event's Wikipedia page URL below it.")]
+    links: bool,
+
+    /// Print events as an aligned table
+    #[arg(long, help = "Print events in aligned Year | Category | Text columns.")]
+    table: bool,
+
+    /// Print each event through a template
+    #[arg(long, value_name = "TEMPLATE", value_parser = output::parse_template, conflicts_with_all = ["table", "group_by", "prefix"], help = "Print each event of text output as TEMPLATE, filling in {year}, {text}, {url}, {category}, {date}, {language} and {id}.")]
+    template: Option<String>,
+
+    /// How --template handles a field the event lacks
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = TemplateMissing::Empty, requires = "template", help = "What to do when an event has no value for a --template placeholder, such as {url} or {year}.")]
+    template_missing: TemplateMissing,
+
+    /// Finish with a one-line overview of the day's events
+    #[arg(long, help = "Print a footer with the year range, total and per-category counts of the day's events.")]
+    summary: bool,
+
+    /// Describe how the events were chosen
+    #[arg(long, help = "After selecting, print to stderr how the event(s) were chosen, e.g. how many candidates there were and how ties were broken.")]
+    explain: bool,
+
+    /// Print category counts instead of events
+    #[arg(long, help = "Print the number of events per category instead of the events themselves (a JSON object with --format json).")]
+    stats: bool,
+
+    /// Report how long the API requests took
+    #[arg(long, help = "Print request timings (time to first byte and total) to stderr; over several requests, the min/median/max.")]
+    timings: bool,
+
+    /// Report how many events the selection was drawn from
+    #[arg(long, help = "After the event(s), print how many were selected out of the available pool to stderr.")]
+    show_total: bool,
+
+    /// Suppress progress and informational messages
+    #[arg(short, long, help = "Only print the events themselves (errors are still reported).")]
+    quiet: bool,
+
+    /// Visualize where the picks fall in the day's span of years
+    #[arg(long, help = "Draw a timeline bar showing where the selected events fall between the oldest and newest year.")]
+    timeline: bool,
+
+    /// Color theme for text output
+    #[arg(long, value_enum, value_name = "NAME", help = "Color theme for text output (overrides the config file's preset).")]
+    theme: Option<ThemePreset>,
+
+    /// Credit Wikipedia after the events
+    #[arg(long, help = "End text output with a footer crediting Wikipedia (CC BY-SA) and linking to the day's feed.")]
+    attribution: bool,
+
+    /// Choose the event to show from a numbered list
+    #[arg(long, conflicts_with_all = ["watch", "all_languages", "archive", "with_meta", "stats", "output", "pick"], help = "List the day's events numbered, ask which one to show, and print it in full with its link.")]
+    menu: bool,
+
+    /// Make html output a complete page
+    #[arg(long, help = "With --format html, print a complete HTML document instead of a fragment.")]
+    full_html: bool,
+
+    /// Record what was queried alongside the events
+    #[arg(long, conflicts_with_all = ["watch", "all_languages", "stats", "archive"], help = "Wrap json output in an object that also records the language, date, event type, fetch time and source URL.")]
+    with_meta: bool,
+
+    /// Keep the feed's categories apart in json output
+    #[arg(long, conflicts_with_all = ["with_meta", "stats", "archive", "all_languages"], help = "Print json output as an object with one list per category (selected, births, deaths, holidays, events) instead of a flat array.")]
+    json_by_category: bool,
+
+    /// Also show the day's featured picture
+    #[arg(long, conflicts_with_all = ["watch", "all_languages", "from_file"], help = "After the events, show the title, description and link of the day's featured picture (text output only).")]
+    picture: bool,
+
+    /// List at most N events, saying how many were left out
+    #[arg(long, value_name = "N", help = "Print at most N of the selected events in text output, followed by how many more there are.")]
+    truncate_list: Option<usize>,
+
+    /// Text to start every event line with
+    #[arg(long, value_name = "STRING", conflicts_with = "table", help = "Start every event line of text output with STRING; {date}, {language}, {category} and {emoji} are filled in.")]
+    prefix: Option<String>,
+
+    /// Mark each event with an emoji for its category
+    #[arg(long, conflicts_with = "table", help = "Start every event line of text output with an emoji for its category (only when colors are on).")]
+    emoji: bool,
+
+    /// How to write the date in the text banner
+    #[arg(long, value_name = "FORMAT", value_parser = date::parse_date_format, help = "strftime format for the date in the text banner, e.g. '%B %-d' (month names follow --language).")]
+    date_format: Option<String>,
+
+    /// Print event text exactly as the API sent it
+    #[arg(long, help = "Don't escape control characters (such as terminal escape sequences) in text and link output.")]
+    raw_text: bool,
+
+    /// Tidy up spacing in event text
+    #[arg(long, help = "Collapse runs of whitespace, including line breaks, in event text to single spaces and trim it (text output only).")]
+    collapse_whitespace: bool,
+
+    /// Apply --collapse-whitespace to structured output too
+    #[arg(long, requires = "collapse_whitespace", help = "Apply --collapse-whitespace to json, jsonl, csv and tsv output as well.")]
+    clean_json: bool,
+
+    /// Leave out the newline after the last line of output
+    #[arg(long, conflicts_with = "watch", help = "Don't end the output with a newline, e.g. when capturing it with $(...).")]
+    no_trailing_newline: bool,
+
+    /// Disable colored output
+    #[arg(long, help = "Disable colored output (also honours the NO_COLOR environment variable).")]
+    no_color: bool,
+
+    /// Show a different day than today
+    #[arg(short, long, value_name = "DATE", value_parser = date::parse_date, help = "Date to show: MM-DD, YYYY-MM-DD, today, yesterday or tomorrow.")]
+    date: Option<date::DateArg>,
+
+    /// Go back some years from the date
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(..=MAX_YEARS_AGO), help = "Query the date N years before --date (today by default); Feb 29 becomes Feb 28 in years without one.")]
+    years_ago: Option<u32>,
+
+    /// Time zone used to work out "today"
+    #[arg(long, value_name = "TZ", default_value = "utc", value_parser = date::parse_timezone, help = "Time zone for today, yesterday and tomorrow: utc, local or an offset like +05:30.")]
+    timezone: date::Timezone,
+
+    /// Pretend the current time is this instant (a testing aid)
+    #[arg(long, value_name = "RFC3339", env = "ON_THIS_DAY_NOW", hide = true, value_parser = parse_now)]
+    now: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Print the date that would be queried and exit
+    #[arg(long, help = "Print the resolved date as MM-DD and exit without fetching.")]
+    print_date: bool,
+
+    /// Per-request timeout
+    #[arg(long, value_name = "SECONDS", help = "Give up on a single API request after SECONDS.")]
+    timeout: Option<u64>,
+
+    /// How often to retry a failed request
+    #[arg(long, value_name = "N", help = "Retry a request up to N times after a connection error, timeout, 429 or 5xx response [default: 0].")]
+    max_retries: Option<u32>,
+
+    /// Base delay between retries
+    #[arg(long, value_name = "MS", help = "Base retry delay in milliseconds; it doubles after every attempt, up to 30 seconds [default: 500].")]
+    retry_backoff: Option<u64>,
+
+    /// A preset for --timeout, --max-retries and --retry-backoff
+    #[arg(long, value_enum, value_name = "PRESET", help = "Set --timeout, --max-retries and --retry-backoff for the connection at once; any of them given explicitly still wins.")]
+    resilience: Option<Resilience>,
+
+    /// Randomization of the retry delays
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = RetryJitter::Full, help = "Randomize retry delays (full) or not (none), so many clients don't retry in lockstep.")]
+    retry_jitter: RetryJitter,
+
+    /// Don't randomize retry delays
+    #[arg(long, conflicts_with = "retry_jitter", help = "Wait exactly the backoff delay between retries; the same as --retry-jitter none.")]
+    no_jitter: bool,
+
+    /// Overall time budget for fetching
+    #[arg(long, value_name = "SECONDS", conflicts_with = "watch", help = "Fail if fetching takes longer than SECONDS in total; in batch mode, the dates completed so far are still shown.")]
+    timeout_total: Option<u64>,
+
+    /// Read events from a local file instead of the API
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["watch", "all_languages", "prefetch_next_day"], help = "Read the day's events from a local JSON file instead of fetching them.")]
+    from_file: Option<PathBuf>,
+
+    /// What a --from-file file holds
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = InputFormat::Api, requires = "from_file", help = "What --from-file holds: a saved API response (api) or a bare array of events (events).")]
+    input_format: InputFormat,
+
+    /// Accept-Language header to send
+    #[arg(long, value_name = "LANGS", help = "Accept-Language header to send with requests, e.g. 'sr-Latn' (defaults to the --language being fetched).")]
+    accept_language: Option<String>,
+
+    /// Base URL of the Wikimedia feed API
+    #[arg(long, value_name = "URL", default_value = fetch::DEFAULT_BASE_URL, help = "Base URL of the feed API, e.g. to point at a mirror or mock server.")]
+    base_url: String,
+
+    /// Don't follow HTTP redirects
+    #[arg(long, help = "Treat HTTP redirects as errors instead of following them.")]
+    no_redirects: bool,
+
+    /// Accept any TLS certificate, for testing only
+    #[arg(long, help = "DANGEROUS: don't verify TLS certificates, e.g. for a local mock server with a self-signed certificate. Never use in production.")]
+    danger_insecure: bool,
+
+    /// Log details of each request
+    #[arg(short, long, conflicts_with = "quiet", help = "Log request details, such as redirects and the final URL, to stderr.")]
+    verbose: bool,
+
+    /// Fail when the filters leave nothing
+    #[arg(long, help = "Exit with an error when the API returned events but the filters removed all of them.")]
+    fail_fast: bool,
+
+    /// Treat data-quality warnings as errors
+    #[arg(long, help = "Exit with a non-zero status if any warning (e.g. skipped or missing data) was reported.")]
+    strict: bool,
+
+    /// Write the output to a file instead of stdout
+    #[arg(short = 'O', long, value_name = "PATH", help = "Write the output to PATH (atomically replacing it) instead of stdout.")]
+    output: Option<PathBuf>,
+
+    /// Feed the output to another program
+    #[arg(long, value_name = "CMD", conflicts_with_all = ["output", "watch", "menu"], help = "Run CMD through the shell and write the output to its stdin instead of stdout, exiting with its status if it fails.")]
+    pipe_to: Option<String>,
+
+    /// Skip the on-disk cache and always hit the API
+    #[arg(long, help = "Always fetch fresh data instead of using the cache.")]
+    no_cache: bool,
+
+    /// List the cache's contents and exit
+    #[arg(long, conflicts_with = "clear_cache", help = "List the cached responses and their total size, then exit (the same as 'cache info').")]
+    cache_info: bool,
+
+    /// Check that the API still answers in the expected shape
+    #[arg(long, help = "Fetch the day's feed and check that it still has the selected, births, deaths, holidays and events lists, then exit with whether it does.")]
+    verify_endpoint: bool,
+
+    /// Check how many events are left after filtering
+    #[arg(long, value_name = "OPN", value_parser = parse_count_assertion, conflicts_with_all = ["watch", "all_languages", "archive", "menu", "with_meta", "fail_fast"], help = "Instead of showing events, check the number left after filtering against OPN (e.g. >=5 or ==0; operators >=, <=, ==, >, <) and exit with whether it holds.")]
+    assert_count: Option<CountAssertion>,
+
+    /// Empty the cache and exit
+    #[arg(long, help = "Delete every cached response after asking for confirmation, then exit (the same as 'cache clear').")]
+    clear_cache: bool,
+
+    /// Warm the cache with tomorrow's feed before exiting
+    #[arg(long, help = "After showing today's events, cache tomorrow's feed so the next run is instant (no-op with --no-cache).")]
+    prefetch_next_day: bool,
+
+    /// How long to cache days without events
+    #[arg(long, value_name = "SECONDS", default_value_t = 3600, help = "Cache responses that contain no events for SECONDS instead of the usual 24 hours.")]
+    empty_cache_ttl: u64,
+
+    /// Directory where cached API responses are stored
+    #[arg(long, value_name = "PATH", help = "Directory for cached responses (defaults to $XDG_CACHE_HOME/on-this-day).")]
+    cache_dir: Option<PathBuf>,
+
+    /// Keep running and show a new event every SECONDS
+    #[arg(long, value_name = "SECONDS", help = "Ambient mode: show a new event every SECONDS, re-fetching when the date changes.")]
+    watch: Option<u64>,
+
+    /// Minimum time between network fetches in watch mode
+    #[arg(long, value_name = "SECONDS", default_value_t = 3600, requires = "watch", help = "In watch mode, make at most one API request per SECONDS, even across date changes.")]
+    min_fetch_interval: u64,
+}
+
+impl Args {
+    /// The request timeout in seconds: `--timeout`, or the `--resilience` preset's.
+    fn timeout(&self) -> Option<u64> {
+        self.timeout.or(self.resilience.map(|preset| preset.settings().0))
+    }
+
+    /// The retry count: `--max-retries`, or the `--resilience` preset's, or 0.
+    fn max_retries(&self) -> u32 {
+        self.max_retries.or(self.resilience.map(|preset| preset.settings().1)).unwrap_or(0)
+    }
+
+    /// The base retry delay in milliseconds: `--retry-backoff`, or the
+    /// `--resilience` preset's, or 500.
+    fn retry_backoff(&self) -> u64 {
+        self.retry_backoff.or(self.resilience.map(|preset| preset.settings().2)).unwrap_or(500)
+    }
+
+    /// The feed to fetch: the `--event-type`'s own if there's just one, and
+    /// otherwise, or under `--search-all`, the whole day's.
+    fn feed_type(&self) -> EventType {
+        match self.event_type.as_slice() {
+            [event_type] if !self.search_all => *event_type,
+            _ => EventType::All,
+        }
+    }
+
+    /// Whether events of `category` are among those asked for.
+    fn wants(&self, category: Category) -> bool {
+        self.search_all || self.event_type.iter().any(|t| t.category().is_none_or(|c| c == category))
+    }
+
+    /// The requested event types as they're recorded in `--archive` and
+    /// `--with-meta` output, e.g. `births,deaths`.
+    fn event_type_label(&self) -> String {
+        if self.search_all {
+            return "all".to_string();
+        }
+        self.event_type.iter().map(|t| t.to_string().to_lowercase()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Whether the day is fetched one feed per `--event-type`.
+    fn separate_feeds(&self) -> bool {
+        self.separate_requests && self.event_type.len() > 1
+    }
+}
+
+/// Parses a `--range` of the form `MM-DD..MM-DD`.
+fn parse_range(value: &str) -> Result<DateRange, String> {
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| "expected a range like 07-01..07-07".to_string())?;
+    let parse_day = |s: &str| -> Result<(u32, u32), String> {
+        let (month, day) = s.trim().split_once('-').ok_or_else(|| format!("'{}' is not an MM-DD date", s))?;
+        let month = month.parse().map_err(|_| format!("'{}' is not an MM-DD date", s))?;
+        let day = day.parse().map_err(|_| format!("'{}' is not an MM-DD date", s))?;
+        // 2024 is a leap year, so 02-29 is accepted.
+        chrono::NaiveDate::from_ymd_opt(2024, month, day).ok_or_else(|| format!("'{}' is not a valid day", s))?;
+        Ok((month, day))
+    };
+    let (start, end) = (parse_day(start)?, parse_day(end)?);
+    // Pick years so the span is contiguous and any 02-29 in it exists.
+    let (start_year, end_year) = match (start > end, start == (2, 29)) {
+        (false, _) => (2024, 2024),
+        (true, false) => (2023, 2024),
+        (true, true) => (2024, 2025),
+    };
+    let date = |year, (month, day)| chrono::NaiveDate::from_ymd_opt(year, month, day);
+    match (date(start_year, start), date(end_year, end)) {
+        (Some(start), Some(end)) => Ok(DateRange { start, end }),
+        _ => Err("02-29 can't end a range that wraps into a non-leap year".to_string()),
+    }
+}
+
+/// Parses `--now`, an RFC 3339 timestamp such as `2024-02-29T23:30:00Z`.
+fn parse_now(value: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|now| now.with_timezone(&chrono::Utc))
+        .map_err(|e| format!("{} (expected an RFC 3339 timestamp such as 2024-02-29T23:30:00Z)", e))
+}
+
+/// Parses `--century` into the century's first year. `19`, `19th` and `1800s`
+/// all give 1800: like `--group-by century`, a century runs from a multiple of
+/// 100 to the 99 after it, not from the 01 to the next 00.
+fn parse_century(value: &str) -> Result<i32, String> {
+    if let Some(start) = value.strip_suffix('s') {
+        if let Ok(start) = start.parse::<i32>() {
+            if start >= 0 && start % 100 == 0 {
+                return Ok(start);
+            }
+            return Err(format!("{} doesn't start a century; use a multiple of 100 such as 1800s", start));
+        }
+    }
+    let number = ["st", "nd", "rd", "th"].iter().find_map(|suffix| value.strip_suffix(suffix)).unwrap_or(value);
+    match number.parse::<i32>() {
+        Ok(n) if (1..=100).contains(&n) => Ok((n - 1) * 100),
+        _ => Err(format!("expected a century such as 19, 19th or 1800s, not '{}'", value)),
+    }
+}
+
+/// Parses `--assert-count`, an operator followed by a count, such as `>=5`.
+fn parse_count_assertion(value: &str) -> Result<CountAssertion, String> {
+    // Two-character operators first, so `>=5` isn't read as `>` and `=5`.
+    let op = [">=", "<=", "==", ">", "<"]
+        .into_iter()
+        .find(|op| value.starts_with(op))
+        .ok_or_else(|| format!("expected an operator (>=, <=, ==, > or <) followed by a count, like >=5, not '{}'", value))?;
+    let n = value[op.len()..].trim().parse().map_err(|e| format!("invalid count in '{}': {}", value, e))?;
+    Ok(CountAssertion { op, n })
+}
+
+/// Parses a `--pick` position, which counts from 1 (or -1 from the end).
+fn parse_pick(value: &str) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(0) => Err("positions start at 1 (or -1 for the last event)".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The main entry point for the asynchronous application.
+/// Parses the command line and runs the requested command.
+pub async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
+    // 1. Parse command-line arguments provided by the user.
+    let cli = Cli::parse();
+    let args = match &cli.command {
+        None => Some(&cli.args),
+        Some(Command::Show(args)) => Some(args.as_ref()),
+        Some(Command::Batch(batch_args)) => Some(&batch_args.args),
+        _ => None,
+    };
+    if let Some(Err(e)) = args.map(validate) {
+        e.exit();
+    }
+    let result = match cli.command {
+        None => run(&cli.args, clock(&cli.args).as_ref()).await,
+        Some(Command::Show(args)) => run(&args, clock(&args).as_ref()).await,
+        Some(Command::Batch(batch_args)) => {
+            let fetcher = fetcher(&batch_args.args)?;
+            batch(&fetcher, &batch_args, clock(&batch_args.args).as_ref()).await
+        }
+        Some(Command::Cache(cache_args)) => cache_command(cache_args.cache_dir.as_deref(), cache_args.action),
+        Some(Command::Completions { shell }) => {
+            // Generated into a buffer, since clap_complete panics on write errors.
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "on-this-day", &mut script);
+            Ok(std::io::stdout().write_all(&script)?)
+        }
+        Some(Command::Manpage) => Ok(clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?),
+    };
+    match result {
+        // A reader like `head` closing the pipe once it has enough isn't a failure.
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) => Ok(()),
+        Err(e) if e.is::<NothingMatched>() => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// Checks the combinations of options clap can't express, failing the way a
+/// parse error would, before anything is fetched.
+fn validate(args: &Args) -> Result<(), clap::Error> {
+    if let (Some(from), Some(to)) = (args.from_year, args.to_year) {
+        if from > to {
+            let message = format!("--from-year {} comes after --to-year {}", from, to);
+            return Err(Cli::command().error(clap::error::ErrorKind::ArgumentConflict, message));
+        }
+    }
+    Ok(())
+}
+
+/// The clock "now" comes from: the real one, or a fixed instant under `--now`.
+fn clock(args: &Args) -> Box<dyn Clock> {
+    match args.now {
+        Some(now) => Box::new(FixedClock(now)),
+        None => Box::new(SystemClock),
+    }
+}
+
+/// Sets up the HTTP client and the response cache, unless the user opted out of it.
+fn fetcher(args: &Args) -> reqwest::Result<Fetcher<'_>> {
+    let cache = if args.no_cache {
+        None
+    } else {
+        Cache::resolve_dir(args.cache_dir.as_deref()).map(Cache::new)
+    };
+    Fetcher::new(args, cache)
+}
+
+/// Runs the `show` command, taking the current time from `clock`.
+async fn run(args: &Args, clock: &dyn Clock) -> Result<(), Box<dyn std::error::Error>> {
+    if args.cache_info {
+        return cache_command(args.cache_dir.as_deref(), CacheAction::Info);
+    }
+    if args.clear_cache {
+        return cache_command(args.cache_dir.as_deref(), CacheAction::Clear { yes: false });
+    }
+    check_event_types(args);
+    if args.json_by_category && args.format == OutputFormat::Jsonl {
+        eprintln!("Error: --json-by-category makes a single JSON object, so it needs --format json, not jsonl.");
+        std::process::exit(1);
+    }
+    let now = clock.now();
+    if args.only_that_year && query_year(args, now).is_none() {
+        eprintln!("Error: --only-that-year needs --years-ago or a --date that includes a year, like 2001-09-11.");
+        std::process::exit(1);
+    }
+
+    if args.print_date {
+        let date = resolve_date(args, clock)?;
+        writeln!(std::io::stdout().lock(), "{:02}-{:02}", date.month(), date.day())?;
+        return Ok(());
+    }
+
+    // 2. Set up the HTTP client and the response cache.
+    let fetcher = fetcher(args)?;
+
+    if args.verify_endpoint {
+        return verify_endpoint(&fetcher, resolve_date(args, clock)?).await;
+    }
+    if let Some(seconds) = args.watch {
+        return watch(&fetcher, clock, seconds).await;
+    }
+
+    // 3. Work out which date to show.
+    let date = resolve_date(args, clock)?;
+    let (month, day) = (date.month(), date.day());
+
+    if args.all_languages {
+        return all_languages(&fetcher, now, month, day).await;
+    }
+
+    // 4. Fetch the events for today, either from the cache or the API.
+    let fetched_at = clock.now();
+    let events = match &args.from_file {
+        Some(path) => match fetcher.load_file(path, month, day) {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("Error: Could not read events from {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => match within_total_timeout(args, fetch_day(&fetcher, &args.language, month, day)).await? {
+            Some(events) => events,
+            None if args.assert_count.is_some() => {
+                // The error has been reported; a probe that couldn't look must fail.
+                eprintln!("Error: Count check failed: the events could not be fetched.");
+                std::process::exit(1);
+            }
+            None => return Ok(()),
+        },
+    };
+
+    // 5. Select events based on the command-line flags and print them.
+    if let Some(assertion) = args.assert_count {
+        let count = filter_events(events, args, now)?.len();
+        if !assertion.holds(count) {
+            eprintln!("Error: Count check failed: {} event(s), expected {}{}.", count, assertion.op, assertion.n);
+            std::process::exit(1);
+        }
+        writeln!(std::io::stdout().lock(), "Count check passed: {} event(s), expected {}{}.", count, assertion.op, assertion.n)?;
+    } else if args.archive {
+        // Archives keep the day exactly as fetched, so filters don't apply.
+        let event_type = args.event_type_label();
+        let query = output::ArchiveQuery { date: date.to_string(), language: &args.language, event_type: &event_type };
+        write_output(args, |out| Ok(output::print_archive(out, &query, fetched_at, &events)?))?;
+    } else if args.menu {
+        menu(&filter_events(events, args, now)?, args)?;
+    } else if args.with_meta && args.format == OutputFormat::Json {
+        let events_to_process = filter_events(events, args, now)?;
+        let selected = select_explained(&events_to_process, args, now);
+        let event_type = args.event_type_label();
+        let source_url = match &args.from_file {
+            Some(path) => path.display().to_string(),
+            None => fetcher.url(&args.language, month, day),
+        };
+        let meta = output::JsonMeta { language: &args.language, date: date.to_string(), event_type: &event_type, source_url };
+        write_output(args, |out| {
+            Ok(output::print_json_with_meta(out, &meta, fetched_at, &selected, args.json_include_url, args.event_id)?)
+        })?;
+    } else {
+        let events_to_process = filter_events(events, args, now)?;
+        let picture = if args.picture && args.format == OutputFormat::Text {
+            fetch_picture(&fetcher, date).await
+        } else {
+            None
+        };
+        write_output(args, |out| {
+            show_events(out, &events_to_process, args, now, month, day)?;
+            if let Some(picture) = &picture {
+                output::print_picture(out, picture, theme(args), args.raw_text)?;
+            }
+            Ok(())
+        })?;
+    }
+    print_timings(&fetcher);
+
+    // 6. Optionally warm the cache for tomorrow, now that the output is out.
+    if args.prefetch_next_day {
+        if let Some(tomorrow) = date.succ_opt() {
+            let prefetch = fetcher.prefetch(&args.language, tomorrow.month(), tomorrow.day());
+            match tokio::time::timeout(PREFETCH_TIMEOUT, prefetch).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) if args.verbose => eprintln!("Prefetch failed: {}", e),
+                Err(_) if args.verbose => eprintln!("Prefetch gave up after {:?}", PREFETCH_TIMEOUT),
+                _ => {}
+            }
+        }
+    }
+
+    exit_if_strict(args);
+    Ok(())
+}
+
+/// Runs the `cache` command.
+fn cache_command(cache_dir: Option<&std::path::Path>, action: CacheAction) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(dir) = Cache::resolve_dir(cache_dir) else {
+        return Err("no cache directory could be determined; pass --cache-dir".into());
+    };
+    let cache = Cache::new(dir);
+    let mut out = std::io::stdout().lock();
+    match action {
+        CacheAction::Info => {
+            let info = cache.info()?;
+            writeln!(out, "Cache directory: {}", cache.dir().display())?;
+            writeln!(out, "Entries: {} ({} fresh)", info.entries, info.fresh)?;
+            writeln!(out, "Size: {} bytes", info.bytes)?;
+            for entry in cache.list()? {
+                writeln!(
+                    out,
+                    "  {:02}-{:02}  {:<4} {:<9} {:>9} bytes  {} old{}",
+                    entry.month,
+                    entry.day,
+                    entry.language,
+                    entry.event_type,
+                    entry.bytes,
+                    format_age(entry.age),
+                    if entry.empty { "  (no events)" } else { "" }
+                )?;
+            }
+        }
+        CacheAction::Clear { yes } => {
+            let count = cache.info()?.entries;
+            if count > 0 && !yes && !confirm(&format!("Delete {} cached response(s) from {}?", count, cache.dir().display()))? {
+                writeln!(out, "Nothing was deleted.")?;
+                return Ok(());
+            }
+            let removed = cache.clear()?;
+            writeln!(out, "Removed {} cached response(s) from {}", removed, cache.dir().display())?;
+        }
+    }
+    Ok(())
+}
+
+/// Asks a yes/no question on stderr and reads the answer from stdin. Anything
+/// but "y" or "yes", including no input at all, counts as no.
+fn confirm(question: &str) -> std::io::Result<bool> {
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Formats an age roughly, in its largest whole unit, e.g. `3h`.
+fn format_age(age: std::time::Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+/// Lists the day's events numbered, asks on stdin which one to show, and
+/// prints that one in full, for `--menu`.
+fn menu(events: &[Event], args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Error: --menu needs an interactive terminal; use --pick N to choose an event non-interactively.");
+        std::process::exit(1);
+    }
+    let mut stdout = std::io::stdout().lock();
+    if events.is_empty() {
+        writeln!(stdout, "No historical events found for today with the selected type.")?;
+        return Ok(());
+    }
+    output::print_menu(&mut stdout, events, theme(args))?;
+    stdout.flush()?;
+
+    eprint!("Show which event? [1-{}] ", events.len());
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let Some(event) = answer.trim().parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| events.get(i)) else {
+        eprintln!("Error: '{}' is not a number between 1 and {}.", answer.trim(), events.len());
+        std::process::exit(1);
+    };
+    output::print_detail(&mut stdout, event, theme(args), args.raw_text)?;
+    Ok(())
+}
+
+/// Probes the API for `--verify-endpoint`, reporting whether its response
+/// still has the layout we parse. Exits with an error if it doesn't.
+async fn verify_endpoint(fetcher: &Fetcher<'_>, date: chrono::NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    let args = fetcher.args;
+    let (url, missing) = match within_total_timeout(args, fetcher.verify_endpoint(&args.language, date.month(), date.day())).await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: Endpoint check failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if missing.is_empty() {
+        writeln!(std::io::stdout().lock(), "Endpoint check passed: {} has the {} lists.", url, fetch::FEED_LISTS.join(", "))?;
+        return Ok(());
+    }
+    if missing.len() == fetch::FEED_LISTS.len() {
+        eprintln!("Error: Endpoint check FAILED: {} has none of the expected lists ({}).", url, missing.join(", "));
+        eprintln!("The API's response format seems to have changed. Please report this at https://github.com/yu-eric/on-this-day/issues.");
+    } else {
+        eprintln!("Error: Endpoint check failed: {} is missing the {} list(s).", url, missing.join(", "));
+    }
+    std::process::exit(1);
+}
+
+/// Rejects an `--event-type` list that names `all` along with other types,
+/// which would ask for some events twice.
+fn check_event_types(args: &Args) {
+    if args.event_type.len() > 1 && args.event_type.iter().any(|t| t.category().is_none()) {
+        eprintln!("Error: --event-type all already includes every type; list specific types instead, like births,deaths.");
+        std::process::exit(1);
+    }
+}
+
+/// Fetches a day's events in `language`: from the one feed the options need,
+/// or, under `--separate-requests`, from each requested type's own feed
+/// concurrently, merged in `--event-type` order. A type that fails is reported
+/// and left out; `Ok(None)` means none could be fetched.
+///
+/// Like [`Fetcher::fetch_events`], an error status has already been reported
+/// when it returns `Ok(None)`, so callers with more to fetch just carry on.
+async fn fetch_day(
+    fetcher: &Fetcher<'_>,
+    language: &str,
+    month: u32,
+    day: u32,
+) -> Result<Option<Vec<Event>>, Box<dyn std::error::Error>> {
+    let args = fetcher.args;
+    if !args.separate_feeds() {
+        return fetcher.fetch_events(language, month, day).await;
+    }
+    let fetches = args.event_type.iter().map(|&event_type| fetcher.fetch_feed(language, event_type, month, day));
+    let mut events: Option<Vec<Event>> = None;
+    for (event_type, result) in args.event_type.iter().zip(futures::future::join_all(fetches).await) {
+        match result {
+            Ok(Some(feed)) => events.get_or_insert_with(Vec::new).extend(feed),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: Failed to fetch '{}' events: {}", event_type.to_string().to_lowercase(), e),
+        }
+    }
+    Ok(events)
+}
+
+/// Fetches the featured picture for `--picture`. A day without one, or a
+/// failed request, is reported and leaves the events to stand on their own.
+async fn fetch_picture(fetcher: &Fetcher<'_>, date: chrono::NaiveDate) -> Option<fetch::Picture> {
+    let args = fetcher.args;
+    match within_total_timeout(args, fetcher.fetch_picture(&args.language, date)).await {
+        Ok(Some(picture)) => Some(picture),
+        Ok(None) => {
+            if !args.quiet {
+                eprintln!("There is no featured picture for {:02}/{:02}.", date.month(), date.day());
+            }
+            None
+        }
+        Err(e) => {
+            eprintln!("Error: Failed to fetch the featured picture: {}", e);
+            None
+        }
+    }
+}
+
+/// Resolves the date to query: `--date` if given, otherwise today in the
+/// `--timezone`, according to the clock.
+fn resolve_date(args: &Args, clock: &dyn Clock) -> Result<chrono::NaiveDate, String> {
+    let date = date::resolve(args.date, args.timezone, clock.now());
+    let Some(years) = args.years_ago else {
+        return Ok(date);
+    };
+    let (earlier, fell_back) = date::years_before(date, years)?;
+    if fell_back && !args.quiet {
+        eprintln!("{} has no February 29; showing February 28 instead.", earlier.year());
+    }
+    Ok(earlier)
+}
+
+/// The year of the queried date, if the user chose one: that of a full
+/// `--date`, or the one `--years-ago` lands on, counting back from `now`.
+fn query_year(args: &Args, now: chrono::DateTime<chrono::Utc>) -> Option<i32> {
+    if let Some(years) = args.years_ago {
+        let date = date::resolve(args.date, args.timezone, now);
+        return date::years_before(date, years).ok().map(|(earlier, _)| earlier.year());
+    }
+    match args.date {
+        Some(date::DateArg::Full(date)) => Some(date.year()),
+        _ => None,
+    }
+}
+
+/// Runs `render` against stdout, or, with `--output`, against a buffer that is
+/// then written to the file atomically so it never holds partial output. With
+/// `--pipe-to`, the buffer is handed to the command instead.
+fn write_output(
+    args: &Args,
+    render: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(command) = &args.pipe_to {
+        let mut buffer = Vec::new();
+        render(&mut buffer)?;
+        if args.no_trailing_newline {
+            buffer.truncate(output::strip_trailing_newline(&buffer).len());
+        }
+        let status = match output::pipe_to(command, &buffer) {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("Error: Could not run '{}': {}", command, e);
+                std::process::exit(1);
+            }
+        };
+        if !status.success() {
+            eprintln!("Error: '{}' failed ({}).", command, status);
+            std::process::exit(status.code().filter(|&code| code != 0).unwrap_or(1));
+        }
+        return Ok(());
+    }
+    match &args.output {
+        None if !args.no_trailing_newline => {
+            let mut stdout = std::io::stdout().lock();
+            render(&mut stdout)?;
+            stdout.flush()?;
+        }
+        None => {
+            // The last line is only known once everything has been rendered.
+            let mut buffer = Vec::new();
+            render(&mut buffer)?;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(output::strip_trailing_newline(&buffer))?;
+            stdout.flush()?;
+        }
+        Some(path) => {
+            let mut buffer = Vec::new();
+            render(&mut buffer)?;
+            if args.no_trailing_newline {
+                buffer.truncate(output::strip_trailing_newline(&buffer).len());
+            }
+            output::write_atomically(path, &buffer)?;
+            if !args.quiet {
+                eprintln!("Wrote output to {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Under `--timings`, prints how long the API requests took to stderr: the
+/// phases of a single request, or the spread of totals over several.
+fn print_timings(fetcher: &Fetcher<'_>) {
+    let args = fetcher.args;
+    if !args.timings || args.quiet {
+        return;
+    }
+    let timings = fetcher.timings();
+    let ms = |d: std::time::Duration| format!("{}ms", d.as_millis());
+    match timings.as_slice() {
+        [] => eprintln!("Timings: no API requests were made (everything came from the cache)."),
+        [timing] => eprintln!("Timings: first byte {}, total {}", ms(timing.ttfb), ms(timing.total)),
+        _ => {
+            let mut totals: Vec<_> = timings.iter().map(|t| t.total).collect();
+            totals.sort();
+            eprintln!(
+                "Timings over {} requests: total min {}, median {}, max {}",
+                totals.len(),
+                ms(totals[0]),
+                ms(totals[totals.len() / 2]),
+                ms(totals[totals.len() - 1])
+            );
+        }
+    }
+}
+
+/// Under `--strict`, exits with a failure status if any warning was reported.
+fn exit_if_strict(args: &Args) {
+    let warnings = WARNINGS.load(Ordering::Relaxed);
+    if args.strict && warnings > 0 {
+        eprintln!("Error: {} warning(s) reported and --strict is set.", warnings);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the ambient display, printing a fresh event every `seconds`.
+///
+/// Events are re-fetched when the date rolls over, but never more often than
+/// `--min-fetch-interval`, so a short interval can't hammer the API around
+/// midnight. Until a re-fetch is allowed, picks keep coming from the old day.
+async fn watch(fetcher: &Fetcher<'_>, clock: &dyn Clock, seconds: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let args = fetcher.args;
+    let min_fetch_interval = std::time::Duration::from_secs(args.min_fetch_interval);
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(seconds.max(1)));
+    let mut last_fetch: Option<std::time::Instant> = None;
+    let mut current: Option<(u32, u32, Vec<Event>)> = None;
+
+    loop {
+        ticker.tick().await;
+
+        let now = clock.now();
+        let date = resolve_date(args, clock)?;
+        let (month, day) = (date.month(), date.day());
+        let is_stale = match &current {
+            Some((m, d, _)) => (*m, *d) != (month, day),
+            None => true,
+        };
+        let may_fetch = last_fetch.is_none_or(|t| t.elapsed() >= min_fetch_interval);
+
+        if is_stale && may_fetch {
+            last_fetch = Some(std::time::Instant::now());
+            match fetch_day(fetcher, &args.language, month, day).await {
+                Ok(Some(events)) => current = Some((month, day, filter_events(events, args, now)?)),
+                // The failure has already been reported; keep showing the old day.
+                Ok(None) => {}
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+
+        let result = write_output(args, |out| match &current {
+            Some((m, d, events)) => show_events(out, events, args, now, *m, *d),
+            None => Ok(writeln!(out, "No historical events found for today with the selected type.")?),
+        });
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+    }
+}
+
+/// Fetches the day's feed in every supported language concurrently and
+/// shows each language's events in its own section. With `--stream`, each
+/// section is printed as soon as its language arrives, fastest first.
+///
+/// Duplicates are removed within each language, but not across languages, so
+/// the same event told by several Wikipedias shows up once per language.
+async fn all_languages(fetcher: &Fetcher<'_>, now: chrono::DateTime<chrono::Utc>, month: u32, day: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let args = fetcher.args;
+    if args.stream && args.format != OutputFormat::Json {
+        within_total_timeout(args, stream_languages(fetcher, now, month, day)).await?;
+        print_timings(fetcher);
+        exit_if_strict(args);
+        return Ok(());
+    }
+    let fetches = ALL_LANGUAGES
+        .iter()
+        .map(|language| fetch_day(fetcher, language, month, day));
+    let results = within_total_timeout(args, futures::future::join_all(fetches)).await;
+
+    let mut per_language: Vec<Vec<Event>> = Vec::new();
+    for (language, result) in ALL_LANGUAGES.iter().zip(results) {
+        match result {
+            Ok(Some(events)) => per_language.push(filter_events(dedup_events(events, args.dedup_key), args, now)?),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: Failed to fetch '{}' events: {}", language, e),
+        }
+    }
+
+    write_output(args, |out| {
+        if args.format == OutputFormat::Text {
+            for events in &per_language {
+                if let Some(first) = events.first() {
+                    writeln!(out, "\n=== Language: {} ===", first.language)?;
+                    show_events(out, events, args, now, month, day)?;
+                }
+            }
+        } else if args.stats {
+            print_stats(out, per_language.iter().flatten(), args)?;
+        } else {
+            // Structured formats get a single document; each event carries its language.
+            let selected: Vec<&Event> = per_language
+                .iter()
+                .flat_map(|events| select_explained(events, args, now))
+                .collect();
+            print_structured(out, &selected, args, true)?;
+        }
+        Ok(())
+    })?;
+
+    print_timings(fetcher);
+    exit_if_strict(args);
+    Ok(())
+}
+
+/// The `--stream` half of [`all_languages`]: prints each language's events
+/// as soon as they have been fetched.
+async fn stream_languages(fetcher: &Fetcher<'_>, now: chrono::DateTime<chrono::Utc>, month: u32, day: u32) -> Result<(), Box<dyn std::error::Error>> {
+    use futures::StreamExt;
+    let args = fetcher.args;
+    let mut fetches: futures::stream::FuturesUnordered<_> = ALL_LANGUAGES
+        .iter()
+        .map(|&language| async move { (language, fetch_day(fetcher, language, month, day).await) })
+        .collect();
+    let mut first_chunk = true;
+    while let Some((language, result)) = fetches.next().await {
+        match result {
+            Ok(Some(events)) => {
+                let events = filter_events(dedup_events(events, args.dedup_key), args, now)?;
+                // A language left with no events gets no section of its own.
+                let heading = events.first().map(|first| format!("\n=== Language: {} ===", first.language));
+                if heading.is_some() || args.format != OutputFormat::Text {
+                    print_streamed(&events, args, now, month, day, heading.as_deref(), &mut first_chunk)?;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: Failed to fetch '{}' events: {}", language, e),
+        }
+    }
+    Ok(())
+}
+
+/// Prints one chunk of streamed events, a day's or a language's, to stdout as
+/// soon as it has arrived, under `heading` in text output. Only the first
+/// chunk's rows get a CSV header; `first_chunk` tracks whether that was it.
+fn print_streamed(
+    events: &[Event],
+    args: &Args,
+    now: chrono::DateTime<chrono::Utc>,
+    month: u32,
+    day: u32,
+    heading: Option<&str>,
+    first_chunk: &mut bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = std::io::stdout().lock();
+    if args.format == OutputFormat::Text {
+        if let Some(heading) = heading {
+            writeln!(out, "{}", heading)?;
+        }
+        show_events(&mut out, events, args, now, month, day)?;
+    } else {
+        print_structured(&mut out, &select_explained(events, args, now), args, *first_chunk)?;
+        *first_chunk = false;
+    }
+    Ok(out.flush()?)
+}
+
+/// Fetches and shows the events for every day in `range`, one day at a time.
+///
+/// With `--stream`, each day is printed as soon as it has been fetched rather
+/// than once the whole batch is done.
+///
+/// Each request is bounded by `--timeout`, and the whole batch by `--deadline`
+/// and `--timeout-total`: once either passes, the remaining days are dropped
+/// and whatever completed is shown, along with how far the batch got. Running
+/// out of `--deadline` is expected; running out of `--timeout-total` fails the run.
+async fn batch(fetcher: &Fetcher<'_>, batch_args: &BatchArgs, clock: &dyn Clock) -> Result<(), Box<dyn std::error::Error>> {
+    let args = fetcher.args;
+    let now = clock.now();
+    check_event_types(args);
+    let days: Vec<(u32, u32)> = batch_args.range.days().collect();
+    let start = tokio::time::Instant::now();
+    let timeout_total = args.timeout_total.map(|seconds| start + std::time::Duration::from_secs(seconds));
+    let deadline = batch_args
+        .deadline
+        .map(|seconds| start + std::time::Duration::from_secs(seconds))
+        .into_iter()
+        .chain(timeout_total)
+        .min();
+
+    // A JSON array can't be printed piecemeal, so it is always buffered.
+    let stream = args.stream && args.format != OutputFormat::Json;
+    let mut per_day: Vec<(u32, u32, Vec<Event>)> = Vec::new();
+    let mut first_chunk = true;
+    let mut completed = 0;
+    for &(month, day) in &days {
+        let fetch = fetch_day(fetcher, &args.language, month, day);
+        let result = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, fetch).await {
+                Ok(result) => result,
+                Err(_) => break,
+            },
+            None => fetch.await,
+        };
+        completed += 1;
+        match result {
+            Ok(Some(events)) if stream => {
+                // Days are fetched in order, so streamed output stays in date order.
+                print_streamed(&filter_events(events, args, now)?, args, now, month, day, None, &mut first_chunk)?;
+            }
+            Ok(Some(events)) => per_day.push((month, day, filter_events(events, args, now)?)),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: Failed to fetch events for {:02}/{:02}: {}", month, day, e),
+        }
+    }
+    let timed_out = completed < days.len() && timeout_total.is_some_and(|t| tokio::time::Instant::now() >= t);
+    if completed < days.len() && !timed_out && !args.quiet {
+        eprintln!("Deadline reached after {} of {} date(s).", completed, days.len());
+    }
+
+    if !stream {
+        write_output(args, |out| {
+            if args.format == OutputFormat::Text {
+                for (month, day, events) in &per_day {
+                    show_events(out, events, args, now, *month, *day)?;
+                }
+            } else if args.stats {
+                print_stats(out, per_day.iter().flat_map(|(_, _, events)| events), args)?;
+            } else {
+                // Structured formats get a single document; each event carries its date.
+                let selected: Vec<&Event> = per_day
+                    .iter()
+                    .flat_map(|(_, _, events)| select_explained(events, args, now))
+                    .collect();
+                print_structured(out, &selected, args, true)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    print_timings(fetcher);
+    if timed_out {
+        eprintln!(
+            "Error: --timeout-total of {}s exceeded after {} of {} date(s).",
+            args.timeout_total.unwrap_or_default(),
+            completed,
+            days.len()
+        );
+        std::process::exit(1);
+    }
+    exit_if_strict(args);
+    Ok(())
+}
+
+/// Awaits `fetch`, the whole fetch phase of a one-day run, exiting with an
+/// error if it takes longer than `--timeout-total`.
+async fn within_total_timeout<T>(args: &Args, fetch: impl std::future::Future<Output = T>) -> T {
+    let Some(seconds) = args.timeout_total else {
+        return fetch.await;
+    };
+    match tokio::time::timeout(std::time::Duration::from_secs(seconds), fetch).await {
+        Ok(result) => result,
+        Err(_) => {
+            eprintln!("Error: Fetching took longer than --timeout-total of {}s.", seconds);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Removes events that duplicate an earlier event under the given key,
+/// keeping the first occurrence.
+fn dedup_events(events: Vec<Event>, key: DedupKey) -> Vec<Event> {
+    let mut seen = std::collections::HashSet::new();
+    events
+        .into_iter()
+        .filter(|e| {
+            let identity = match key {
+                DedupKey::YearText => (e.year, e.text.clone()),
+                DedupKey::Text => (None, e.text.clone()),
+                DedupKey::NormalizedText => (None, normalize_text(&e.text)),
+            };
+            seen.insert(identity)
+        })
+        .collect()
+}
+
+/// Lowercases `text` and drops punctuation and whitespace, so that
+/// differently phrased or punctuated copies of an event compare equal.
+fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The seed `--daily` picks with, derived from the queried date as
+/// `YYYYMMDD`, so it changes every day but is the same for everyone.
+fn daily_seed(args: &Args, now: chrono::DateTime<chrono::Utc>, events: &[Event]) -> Option<u64> {
+    if !args.daily {
+        return None;
+    }
+    let first = events.first()?;
+    let year = query_year(args, now).unwrap_or_else(|| date::resolve(None, args.timezone, now).year());
+    Some(year as u64 * 10000 + u64::from(first.month) * 100 + u64::from(first.day))
+}
+
+/// The year range `--clamp-years` filters to: `from..=to` if any event falls
+/// in it, otherwise the range stretched on the near side just far enough to
+/// take in the closest dated events, with a warning.
+fn clamp_years(events: &[Event], from: i32, to: i32, args: &Args) -> (i32, i32) {
+    let years = || events.iter().filter_map(|e| e.year);
+    if years().any(|year| (from..=to).contains(&year)) {
+        return (from, to);
+    }
+    let distance = |year: i32| if year < from { i64::from(from) - i64::from(year) } else { i64::from(year) - i64::from(to) };
+    let Some(nearest) = years().map(distance).min() else {
+        return (from, to);
+    };
+    let nearest = |year: &i32| distance(*year) == nearest;
+    let widened = (years().filter(nearest).min().unwrap_or(from).min(from), years().filter(nearest).max().unwrap_or(to).max(to));
+    let describe = |(from, to): (i32, i32)| match (from, to) {
+        (i32::MIN, to) => format!("up to {}", to),
+        (from, i32::MAX) => format!("from {} on", from),
+        (from, to) => format!("from {} to {}", from, to),
+    };
+    warn(args, &format!("No events {}; showing events {} instead, the nearest there are.", describe((from, to)), describe(widened)));
+    widened
+}
+
+/// The single year events are limited to: `--year`, or the year of the
+/// queried date under `--only-that-year`.
+fn year_filter(args: &Args, now: chrono::DateTime<chrono::Utc>) -> Option<i32> {
+    if args.only_that_year {
+        query_year(args, now)
+    } else {
+        args.year
+    }
+}
+
+/// Narrows the fetched events down to the pool selection draws from,
+/// according to the filtering flags. `now` is the present `--years-ago`
+/// counts back from.
+///
+/// Fails with [`NothingMatched`] under `--fail-fast` when events were fetched
+/// but the filters removed every one.
+pub fn filter_events(mut events: Vec<Event>, args: &Args, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<Event>, NothingMatched> {
+    let fetched = events.len();
+    // Whether a filter has already said it left nothing.
+    let mut explained = false;
+    if args.dedup {
+        events = dedup_events(events, args.dedup_key);
+    }
+    if args.format == OutputFormat::Link {
+        // Only events with a page can be listed, so pick among those.
+        events.retain(|e| e.url().is_some());
+    }
+    if !args.exclude.is_empty() || args.no_holidays {
+        let excluded = |e: &Event| args.exclude.contains(&e.category) || (args.no_holidays && e.category == Category::Holidays);
+        events.retain(|e| !excluded(e));
+    }
+    if args.dedupe_by_page {
+        // Keep the first event per primary page; events without a page always stay.
+        let mut seen_pages = std::collections::HashSet::new();
+        events.retain(|e| e.url().is_none_or(|url| seen_pages.insert(url.to_string())));
+    }
+    if let Some(parity) = args.year_parity {
+        let wanted = if parity == Parity::Even { 0 } else { 1 };
+        events.retain(|e| e.year.is_some_and(|year| year.rem_euclid(2) == wanted));
+    }
+    if let Some(year) = year_filter(args, now) {
+        events.retain(|e| e.year == Some(year));
+    }
+    if args.from_year.is_some() || args.to_year.is_some() {
+        let from = args.from_year.unwrap_or(i32::MIN);
+        let to = args.to_year.unwrap_or(i32::MAX);
+        let (from, to) = if args.clamp_years { clamp_years(&events, from, to, args) } else { (from, to) };
+        events.retain(|e| e.year.is_some_and(|year| (from..=to).contains(&year)));
+    }
+    if let Some(start) = args.century {
+        events.retain(|e| e.year.is_some_and(|year| year.div_euclid(100) * 100 == start));
+    }
+    if let Some(text) = &args.contains {
+        let needle = text.to_lowercase();
+        let matches = |e: &Event| e.text.to_lowercase().contains(&needle);
+        let matching = events.iter().filter(|e| matches(e)).count();
+        if args.contains_else_random && !args.quiet {
+            if matching == 0 {
+                eprintln!("No events contain '{}'; picking at random from all {} event(s) instead.", text, events.len());
+            } else {
+                eprintln!("{} event(s) contain '{}'; picking from those.", matching, text);
+            }
+        }
+        if matching == 0 && !args.contains_else_random && args.feed_type().category().is_some() && !events.is_empty() && !args.quiet {
+            eprintln!("No events contain '{}'; add --search-all to search the other categories too.", text);
+            explained = true;
+        }
+        if matching > 0 || !args.contains_else_random {
+            events.retain(|e| matches(e));
+        }
+    }
+    if args.min_text_length.is_some() || args.max_text_length.is_some() {
+        let before = events.len();
+        let min = args.min_text_length.unwrap_or(0);
+        let max = args.max_text_length.unwrap_or(usize::MAX);
+        events.retain(|e| (min..=max).contains(&e.text.chars().count()));
+        if before > 0 && events.is_empty() && !args.quiet {
+            eprintln!("All {} event(s) were dropped by --min-text-length/--max-text-length.", before);
+            explained = true;
+        }
+    }
+    if let Some(order) = args.sort {
+        // Stable sorts, so equal events keep their feed order.
+        let text_len = |e: &Event| e.text.chars().count();
+        match order {
+            SortOrder::Asc => events.sort_by_key(|e| (e.year.is_none(), e.year)),
+            SortOrder::Desc => events.sort_by_key(|e| (e.year.is_none(), e.year.map(std::cmp::Reverse))),
+            SortOrder::Length => events.sort_by_key(text_len),
+            SortOrder::LengthDesc => events.sort_by_key(|e| std::cmp::Reverse(text_len(e))),
+        }
+    }
+    if let Some(n) = args.head {
+        events.truncate(n);
+    }
+    if args.collapse_whitespace && (args.format == OutputFormat::Text || args.clean_json) {
+        for event in &mut events {
+            event.retext(output::collapse_whitespace(&event.text));
+        }
+    }
+    if fetched > 0 && events.is_empty() {
+        // Tell "the filters removed everything" apart from "the API had nothing".
+        if args.fail_fast {
+            return Err(NothingMatched { fetched });
+        }
+        if !explained && !args.quiet {
+            eprintln!("{}", NothingMatched { fetched });
+        }
+    }
+    if matches!(args.format, OutputFormat::Text | OutputFormat::Link) && !args.raw_text {
+        // JSON escapes control characters itself, and CSV is meant for files.
+        for event in &mut events {
+            event.retext(output::sanitize(&event.text));
+        }
+    }
+    Ok(events)
+}
+
+/// The `--fail-fast` failure: the API returned events, but the filters
+/// removed all of them.
+#[derive(Debug)]
+pub struct NothingMatched {
+    /// How many events were fetched.
+    pub fetched: usize,
+}
+
+impl std::fmt::Display for NothingMatched {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} event(s) were returned but none matched your filters; try relaxing them.", self.fetched)
+    }
+}
+
+impl std::error::Error for NothingMatched {}
+
+/// How a selection was made, for `--explain`.
+pub struct Rationale {
+    method: Method,
+    /// How many events the method chose among.
+    candidates: usize,
+    /// How many events were selected.
+    selected: usize,
+    /// How many candidates ranked equal to the last event selected.
+    tied: usize,
+    tie_break: TieBreak,
+    selected_first: bool,
+}
+
+/// The selection methods, mirroring the selection flags.
+enum Method {
+    Position(i64),
+    Closest(i32),
+    Percentile(u8),
+    Oldest,
+    Newest,
+    Random { seed: Option<u64>, skip: usize, weighted: bool },
+}
+
+impl std::fmt::Display for Rationale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let candidates = self.candidates;
+        match self.method {
+            Method::Position(n) => write!(f, "position {} in feed order among {} events", n, candidates)?,
+            Method::Closest(year) => write!(f, "closest to {} among {} dated events", year, candidates)?,
+            Method::Percentile(p) => write!(f, "percentile {} by year among {} dated events", p, candidates)?,
+            Method::Oldest => write!(f, "oldest by year among {} dated events", candidates)?,
+            Method::Newest => write!(f, "newest by year among {} dated events", candidates)?,
+            Method::Random { seed, skip, weighted } => {
+                let kind = if weighted { "recency-weighted random pick" } else { "random pick" };
+                match self.selected {
+                    1 => write!(f, "{} from {} candidates", kind, candidates)?,
+                    n => write!(f, "{} of {} from {} candidates", kind, n, candidates)?,
+                }
+                match seed {
+                    Some(seed) => write!(f, " (seed={}", seed)?,
+                    None => write!(f, " (seed=none")?,
+                }
+                if skip > 0 {
+                    write!(f, ", skipped {}", skip)?;
+                }
+                write!(f, ")")?;
+            }
+        }
+        if self.tied > 1 {
+            let rule = self.tie_break.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+            write!(f, " ({} tied, broken by --tie-break {})", self.tied, rule)?;
+        }
+        if self.selected_first {
+            write!(f, "; selected events listed first")?;
+        }
+        Ok(())
+    }
+}
+
+/// Selects events like [`select_events`], printing how they were chosen to
+/// stderr under `--explain`.
+fn select_explained<'a>(events_to_process: &'a [Event], args: &Args, now: chrono::DateTime<chrono::Utc>) -> Vec<&'a Event> {
+    let (selected, rationale) = select_events(events_to_process, args, now);
+    if args.explain && !args.quiet {
+        eprintln!("Explain: {}", rationale);
+    }
+    selected
+}
+
+/// Selects up to `--count` events based on the command-line flags, along with
+/// how they were chosen. An empty result means no event could be selected.
+/// `now` dates the `--daily` seed when the query has no year of its own.
+pub fn select_events<'a>(events_to_process: &'a [Event], args: &Args, now: chrono::DateTime<chrono::Utc>) -> (Vec<&'a Event>, Rationale) {
+    let count = if args.count == 0 { events_to_process.len() } else { args.count };
+    let rank = |key: &dyn Fn(i32) -> i64| rank_dated(events_to_process, args.tie_break, count, key);
+
+    let (mut selected, method, candidates, tied) = if let Some(n) = args.pick {
+        // Counting from 1, or back from the end for negative positions.
+        let index = if n > 0 { n - 1 } else { events_to_process.len() as i64 + n };
+        let selected = usize::try_from(index)
+            .ok()
+            .and_then(|i| events_to_process.get(i))
+            .into_iter()
+            .collect();
+        (selected, Method::Position(n), events_to_process.len(), 0)
+    } else if let Some(target) = args.near_year {
+        // Rank dated events by their distance from the requested year.
+        let (selected, dated, tied) = rank(&|year| (i64::from(year) - i64::from(target)).abs());
+        (selected, Method::Closest(target), dated, tied)
+    } else if let Some(p) = args.percentile {
+        let (sorted, dated, _) = rank_dated(events_to_process, args.tie_break, usize::MAX, &i64::from);
+        // Nearest rank: 0 is the oldest dated event and 100 the newest.
+        let chosen = dated.checked_sub(1).map(|last| sorted[(last * usize::from(p) + 50) / 100]);
+        let tied = chosen.map_or(0, |chosen| sorted.iter().filter(|e| e.year == chosen.year).count());
+        (chosen.into_iter().collect(), Method::Percentile(p), dated, tied)
+    } else if let Some(n) = args.top_oldest {
+        let (selected, dated, tied) = rank_dated(events_to_process, args.tie_break, n, &i64::from);
+        (selected, Method::Oldest, dated, tied)
+    } else if let Some(n) = args.top_newest {
+        let (mut selected, dated, tied) = rank_dated(events_to_process, args.tie_break, n, &|year| -i64::from(year));
+        // Shown as a timeline, so the newest event comes last.
+        selected.reverse();
+        (selected, Method::Newest, dated, tied)
+    } else if args.oldest {
+        let (selected, dated, tied) = rank(&i64::from);
+        (selected, Method::Oldest, dated, tied)
+    } else if args.newest {
+        let (selected, dated, tied) = rank(&|year| -i64::from(year));
+        (selected, Method::Newest, dated, tied)
+    } else {
+        // Default behavior: select random events, shown in feed order.
+        let seed = args.seed.or_else(|| daily_seed(args, now, events_to_process));
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let amount = count.min(events_to_process.len());
+        let weights = if args.weight_recency { recency_weights(events_to_process) } else { Vec::new() };
+        if args.cover_categories && !args.quiet {
+            let categories = categories_in(events_to_process).len();
+            if amount < categories {
+                eprintln!(
+                    "Can't cover all {} categories with {} event(s); covering the first {}.",
+                    categories, amount, amount
+                );
+            }
+        }
+        if let (Some(k), false) = (args.min_distinct_years, args.quiet) {
+            let years = distinct_years(events_to_process.iter().filter_map(|e| e.year));
+            if amount.min(years) < k {
+                eprintln!(
+                    "Can't span {} distinct years with {} event(s) from a pool covering {} year(s); the pick spans {}.",
+                    k,
+                    amount,
+                    years,
+                    amount.min(years)
+                );
+            }
+        }
+        let sample = |rng: &mut StdRng| -> Vec<usize> {
+            if let Some(k) = args.min_distinct_years {
+                sample_distinct_years(rng, events_to_process, amount, k)
+            } else if args.cover_categories {
+                sample_covering(rng, events_to_process, amount)
+            } else if args.weight_recency {
+                let all: Vec<usize> = (0..events_to_process.len()).collect();
+                all.choose_multiple_weighted(rng, amount, |&i| weights[i])
+                    .map(|chosen| chosen.copied().collect())
+                    .unwrap_or_default()
+            } else {
+                rand::seq::index::sample(rng, events_to_process.len(), amount).into_vec()
+            }
+        };
+        // Advance the generator past the picks the user asked to skip.
+        for _ in 0..args.skip {
+            sample(&mut rng);
+        }
+        let mut indices = sample(&mut rng);
+        indices.sort_unstable();
+        let selected = indices.into_iter().map(|i| &events_to_process[i]).collect();
+        let method = Method::Random { seed, skip: args.skip, weighted: args.weight_recency };
+        (selected, method, events_to_process.len(), 0)
+    };
+
+    if args.selected_first {
+        // A stable sort keeps the chosen order within both groups.
+        selected.sort_by_key(|e| e.category != Category::Selected);
+    }
+    let rationale = Rationale {
+        method,
+        candidates,
+        selected: selected.len(),
+        tied,
+        tie_break: args.tie_break,
+        selected_first: args.selected_first,
+    };
+    (selected, rationale)
+}
+
+/// The indices of the events in each category present, with the categories
+/// in the order they first appear.
+fn categories_in(events: &[Event]) -> Vec<Vec<usize>> {
+    let mut categories: Vec<(Category, Vec<usize>)> = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        match categories.iter_mut().find(|(category, _)| *category == event.category) {
+            Some((_, indices)) => indices.push(i),
+            None => categories.push((event.category, vec![i])),
+        }
+    }
+    categories.into_iter().map(|(_, indices)| indices).collect()
+}
+
+/// Randomly picks `amount` events for `--cover-categories`: first one from
+/// each category, as far as `amount` goes, then the rest from whatever is left.
+fn sample_covering(rng: &mut StdRng, events: &[Event], amount: usize) -> Vec<usize> {
+    let mut chosen: Vec<usize> = categories_in(events)
+        .iter()
+        .take(amount)
+        .filter_map(|indices| indices.choose(rng).copied())
+        .collect();
+    let rest: Vec<usize> = (0..events.len()).filter(|i| !chosen.contains(i)).collect();
+    chosen.extend(rest.choose_multiple(rng, amount - chosen.len()));
+    chosen
+}
+
+/// Randomly picks `amount` events for `--min-distinct-years`, passing over
+/// events from a year already picked (or undated ones) while the remaining slots
+/// are needed to reach `k` years. If the pool runs out first, the passed-over
+/// events fill the rest.
+fn sample_distinct_years(rng: &mut StdRng, events: &[Event], amount: usize, k: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.shuffle(rng);
+    let mut chosen = Vec::with_capacity(amount);
+    let mut years = std::collections::HashSet::new();
+    let mut passed_over = Vec::new();
+    for i in order {
+        if chosen.len() == amount {
+            break;
+        }
+        let new_year = events[i].year.is_some_and(|year| !years.contains(&year));
+        let spare = amount - chosen.len() > k.saturating_sub(years.len());
+        if new_year || spare {
+            if let Some(year) = events[i].year {
+                years.insert(year);
+            }
+            chosen.push(i);
+        } else {
+            passed_over.push(i);
+        }
+    }
+    let missing = amount - chosen.len();
+    chosen.extend(passed_over.into_iter().take(missing));
+    chosen
+}
+
+/// The number of different years among `years`.
+fn distinct_years(years: impl Iterator<Item = i32>) -> usize {
+    years.collect::<std::collections::HashSet<_>>().len()
+}
+
+/// Weights for `--weight-recency`: a dated event weighs one more than the
+/// number of years it came after the oldest one, so the newest events are the
+/// most likely picks; undated events get the oldest event's weight of one.
+fn recency_weights(events: &[Event]) -> Vec<f64> {
+    let min_year = events.iter().filter_map(|e| e.year).min().unwrap_or(0);
+    events
+        .iter()
+        .map(|e| e.year.map_or(1.0, |year| f64::from(year - min_year) + 1.0))
+        .collect()
+}
+
+/// Returns up to `count` dated events, best first, ordered by `key` applied to
+/// their year. Events without a year are ignored, and events with equal keys
+/// are ordered by the tie-break rule. Also returns how many events were dated
+/// and how many shared the key of the last event returned.
+fn rank_dated<'a>(
+    events_to_process: &'a [Event],
+    tie_break: TieBreak,
+    count: usize,
+    key: &dyn Fn(i32) -> i64,
+) -> (Vec<&'a Event>, usize, usize) {
+    let mut dated: Vec<(usize, &Event, i64)> = events_to_process
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| e.year.map(|year| (i, e, key(year))))
+        .collect();
+    dated.sort_by(|(a_index, a, a_key), (b_index, b, b_key)| {
+        a_key.cmp(b_key).then_with(|| tie_break.compare((*a_index, a), (*b_index, b)))
+    });
+    let total = dated.len();
+    let last_key = count.min(total).checked_sub(1).map(|i| dated[i].2);
+    let tied = dated.iter().filter(|(_, _, k)| Some(*k) == last_key).count();
+    (dated.into_iter().take(count).map(|(_, e, _)| e).collect(), total, tied)
+}
+
+/// Selects events from the pool, prints them and reports totals if asked to.
+fn show_events(
+    out: &mut dyn Write,
+    events_to_process: &[Event],
+    args: &Args,
+    now: chrono::DateTime<chrono::Utc>,
+    month: u32,
+    day: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.stats {
+        return Ok(print_stats(out, events_to_process, args)?);
+    }
+    if events_to_process.is_empty() && args.format == OutputFormat::Text {
+        writeln!(out, "No historical events found for today with the selected type.")?;
+        return Ok(());
+    }
+
+    let mut selected_events = select_explained(events_to_process, args, now);
+    let mut hidden = 0;
+    if let (Some(limit), OutputFormat::Text) = (args.truncate_list, args.format) {
+        hidden = selected_events.len().saturating_sub(limit);
+        selected_events.truncate(limit);
+    }
+    print_events(out, &selected_events, args, now, month, day)?;
+    if hidden > 0 {
+        // With every event already selected, only the list limit hides any.
+        let hint = if args.count == 0 { "raise --truncate-list to see more" } else { "use --count 0 to see all" };
+        writeln!(out, "… and {} more ({})", hidden, hint)?;
+    }
+
+    if args.timeline && args.format == OutputFormat::Text {
+        output::print_timeline(out, events_to_process, &selected_events, output::terminal_width(), use_color(args))?;
+    }
+
+    if args.summary && !args.quiet && args.format == OutputFormat::Text {
+        output::print_summary(out, events_to_process)?;
+    }
+
+    if args.attribution && !args.quiet && args.format == OutputFormat::Text {
+        let language = events_to_process.first().map_or(args.language.as_str(), |e| e.language.as_str());
+        output::print_attribution(out, &fetch::feed_url(args, language, month, day))?;
+    }
+
+    if args.show_total && !args.quiet && !selected_events.is_empty() {
+        eprintln!(
+            "(selected {} of {} events for {:02}/{:02})",
+            selected_events.len(),
+            events_to_process.len(),
+            month,
+            day
+        );
+    }
+    Ok(())
+}
+
+/// Whether to emit ANSI colors: only when printing to a terminal, and never
+/// when the user opted out with `--no-color` or the `NO_COLOR` convention.
+fn use_color(args: &Args) -> bool {
+    !args.no_color && args.output.is_none() && args.pipe_to.is_none() && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Prints the selected events to the console in the requested format.
+fn print_events(out: &mut dyn Write, selected_events: &[&Event], args: &Args, now: chrono::DateTime<chrono::Utc>, month: u32, day: u32) -> std::io::Result<()> {
+    match args.format {
+        // JSON consumers get an empty array rather than a message.
+        OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Html | OutputFormat::Link => {
+            print_structured(out, selected_events, args, true)?
+        }
+        OutputFormat::Text if selected_events.is_empty() => {
+            // This is a fallback, e.g. if --oldest is used with --event-type holidays
+            eprintln!("Could not select an event from the available data.");
+        }
+        OutputFormat::Text if args.template.is_some() => {
+            output::print_template(out, selected_events, args.template.as_deref().unwrap_or_default(), args.template_missing)?;
+        }
+        // Separately fetched types get a section each unless laid out otherwise.
+        OutputFormat::Text if args.group_by.is_some() || (args.separate_feeds() && !args.table) => {
+            let group_by = args.group_by.unwrap_or(GroupBy::Category);
+            output::print_grouped(out, selected_events, &header_date(args, now, month, day), group_by, theme(args), args.links, line_prefix(args).as_deref())?;
+        }
+        OutputFormat::Text if args.table => {
+            output::print_table(out, selected_events, &header_date(args, now, month, day), output::terminal_width(), theme(args))?;
+        }
+        OutputFormat::Text => output::print_events(out, selected_events, &header_date(args, now, month, day), theme(args), args.links, line_prefix(args).as_deref())?,
+    }
+    Ok(())
+}
+
+/// What each event line starts with: the `--prefix`, if any, after the
+/// category's emoji under `--emoji`. Like colors, the emoji are left out when
+/// the output isn't going to a terminal.
+fn line_prefix(args: &Args) -> Option<String> {
+    match (&args.prefix, args.emoji && use_color(args)) {
+        (Some(prefix), true) => Some(format!("{{emoji}} {}", prefix)),
+        (None, true) => Some("{emoji} ".to_string()),
+        (prefix, false) => prefix.clone(),
+    }
+}
+
+/// The date shown in the text banner: `MM/DD`, or formatted per `--date-format`
+/// in the `--language`'s locale. Without a year from `--date` or `--years-ago`,
+/// the current one is used.
+fn header_date(args: &Args, now: chrono::DateTime<chrono::Utc>, month: u32, day: u32) -> String {
+    let Some(format) = &args.date_format else {
+        return format!("{:02}/{:02}", month, day);
+    };
+    let date = query_year(args, now)
+        .and_then(|year| chrono::NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap_or_else(|| date::resolve(Some(date::DateArg::MonthDay(month, day)), args.timezone, now));
+    date::format_localized(date, format, &args.language)
+}
+
+/// Prints how many events of each category the pool holds, for `--stats`.
+fn print_stats<'a>(out: &mut dyn Write, pool: impl IntoIterator<Item = &'a Event>, args: &Args) -> std::io::Result<()> {
+    let counts = output::CategoryCounts::of(pool);
+    match args.format {
+        OutputFormat::Text => output::print_counts_text(out, &counts),
+        OutputFormat::Json => output::print_counts_json(out, &counts, true),
+        OutputFormat::Jsonl => output::print_counts_json(out, &counts, false),
+        OutputFormat::Csv => output::print_counts_delimited(out, &counts, ',', args.bom),
+        OutputFormat::Tsv => output::print_counts_delimited(out, &counts, '\t', args.bom),
+        OutputFormat::Html => output::print_counts_html(out, &counts),
+        OutputFormat::Link => output::print_counts_text(out, &counts),
+    }
+}
+
+/// Prints the selected events in a structured format. Each event carries its own
+/// language and date, so events from several feeds can share one document.
+/// Without `start`, the rows continue an earlier CSV/TSV document, so no header
+/// is printed.
+fn print_structured(out: &mut dyn Write, selected_events: &[&Event], args: &Args, start: bool) -> std::io::Result<()> {
+    match args.format {
+        OutputFormat::Json if args.json_by_category => output::print_json_by_category(out, selected_events),
+        OutputFormat::Json => output::print_json(out, selected_events, args.json_include_url, args.event_id),
+        OutputFormat::Jsonl => output::print_jsonl(out, selected_events, args.json_include_url, args.event_id),
+        OutputFormat::Csv => output::print_delimited(out, selected_events, ',', args.bom, start),
+        OutputFormat::Tsv => output::print_delimited(out, selected_events, '\t', args.bom, start),
+        OutputFormat::Html => {
+            let grouped = matches!(args.feed_type(), EventType::All);
+            output::print_html(out, selected_events, args.links, grouped, args.full_html.then_some(args.language.as_str()))
+        }
+        OutputFormat::Link => output::print_links(out, selected_events),
+        OutputFormat::Text => unreachable!("text output is printed by print_events"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    /// Parses options the way the command line would.
+    fn args(flags: &[&str]) -> Args {
+        Cli::parse_from(std::iter::once("on-this-day").chain(flags.iter().copied())).args
+    }
+
+    /// A clock stopped half an hour before midnight UTC on a leap day.
+    fn leap_day_clock() -> FixedClock {
+        FixedClock(Utc.with_ymd_and_hms(2024, 2, 29, 23, 30, 0).unwrap())
+    }
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn resolve_date_defaults_to_the_clocks_day() {
+        assert_eq!(resolve_date(&args(&["--timezone", "utc"]), &leap_day_clock()).unwrap(), ymd(2024, 2, 29));
+    }
+
+    #[test]
+    fn resolve_date_reads_the_clock_in_the_timezone() {
+        assert_eq!(resolve_date(&args(&["--timezone", "+05:30"]), &leap_day_clock()).unwrap(), ymd(2024, 3, 1));
+        assert_eq!(resolve_date(&args(&["--timezone=-01:00"]), &leap_day_clock()).unwrap(), ymd(2024, 2, 29));
+    }
+
+    #[test]
+    fn resolve_date_prefers_an_explicit_date() {
+        assert_eq!(resolve_date(&args(&["--date", "07-20"]), &leap_day_clock()).unwrap(), ymd(2024, 7, 20));
+    }
+
+    #[test]
+    fn resolve_date_counts_years_ago_from_the_clock() {
+        let args = args(&["--timezone", "utc", "--years-ago", "1", "--quiet"]);
+        assert_eq!(resolve_date(&args, &leap_day_clock()).unwrap(), ymd(2023, 2, 28));
+    }
+
+    #[test]
+    fn tidying_text_for_display_keeps_the_id() {
+        let mut event: Event = serde_json::from_str(r#"{"text": "Two  spaces\u001b[31m", "year": 1969}"#).unwrap();
+        let id = event.id();
+        event.retext(output::collapse_whitespace(&event.text));
+        event.retext(output::sanitize(&event.text));
+        assert_ne!(event.text, "Two  spaces\u{1b}[31m");
+        assert_eq!(event.id(), id);
+    }
+
+    #[test]
+    fn fail_fast_reports_filters_that_removed_everything() {
+        let events: Vec<Event> = serde_json::from_str(r#"[{"text": "Something happens.", "year": 1066}]"#).unwrap();
+        let now = leap_day_clock().now();
+        let result = filter_events(events, &args(&["--contains", "nothing like it", "--fail-fast", "--quiet"]), now);
+        assert!(matches!(result, Err(NothingMatched { fetched: 1 })));
+    }
+}
@@ -0,0 +1,806 @@
+use crate::fetch::Picture;
+use crate::theme::{paint, Theme};
+use crate::{Category, Event, GroupBy, TemplateMissing};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Width to assume when the terminal size can't be detected (e.g. when piped).
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// Narrowest the text column of a table is allowed to get before wrapping
+/// stops being useful.
+const MIN_TEXT_WIDTH: usize = 20;
+
+/// Returns the width of the attached terminal, or a sensible default.
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(w), _)| w as usize)
+        .unwrap_or(DEFAULT_TERMINAL_WIDTH)
+}
+
+/// Writes `contents` to `path` through a temporary file in the same directory
+/// that is renamed into place once complete, so an interrupted or failed run
+/// never leaves a half-written file for a downstream reader.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    // Temporary files are private to the user; ask for the mode a newly
+    // created file gets instead, which the umask then narrows as usual.
+    #[cfg(unix)]
+    let mut file = {
+        use std::os::unix::fs::PermissionsExt;
+        tempfile::Builder::new().permissions(std::fs::Permissions::from_mode(0o666)).tempfile_in(dir)?
+    };
+    #[cfg(not(unix))]
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    // A file being replaced keeps its permissions.
+    if let Ok(existing) = std::fs::metadata(path) {
+        file.as_file().set_permissions(existing.permissions())?;
+    }
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Runs `command` through the platform shell with `contents` on its stdin and
+/// waits for it to exit. Its own stdout and stderr are left attached to ours.
+pub fn pipe_to(command: &str, contents: &[u8]) -> std::io::Result<std::process::ExitStatus> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell.arg(command).stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // A command that exits without reading all of its input isn't an error
+    // here; its exit status says whether it worked.
+    match stdin.write_all(contents) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+        _ => {}
+    }
+    // Closing stdin lets the command see the end of its input.
+    drop(stdin);
+    child.wait()
+}
+
+/// Drops the final line break (`\n` or `\r\n`) from rendered output, if it has one.
+pub fn strip_trailing_newline(output: &[u8]) -> &[u8] {
+    let output = output.strip_suffix(b"\n").unwrap_or(output);
+    output.strip_suffix(b"\r").unwrap_or(output)
+}
+
+/// Replaces every run of whitespace in `text`, line breaks included, with a
+/// single space, and trims both ends.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Makes API text safe to print to a terminal by escaping control characters,
+/// such as the ESC that starts an escape sequence, as e.g. `\u{1b}`. Tabs and
+/// line breaks are left alone.
+pub fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() && !matches!(c, '\t' | '\n' | '\r') {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Text from the API as it should be printed: [`sanitize`]d, unless `raw`
+/// (`--raw-text`) asks for it exactly as received.
+fn display_text(text: &str, raw: bool) -> String {
+    if raw {
+        text.to_string()
+    } else {
+        sanitize(text)
+    }
+}
+
+/// Prints the selected events as free-form lines under a date header.
+pub fn print_events(
+    out: &mut dyn Write,
+    events: &[&Event],
+    date: &str,
+    theme: &Theme,
+    links: bool,
+    prefix: Option<&str>,
+) -> std::io::Result<()> {
+    print_header(out, date, theme)?;
+    writeln!(out)?;
+    for event in events {
+        print_event_line(out, event, theme, links, prefix)?;
+    }
+    Ok(())
+}
+
+/// Prints the selected events in headed sections, grouped by category, decade
+/// or century. Categories keep the feed's order; decades and centuries run
+/// oldest first, with undated events collected in a final "Undated" section.
+pub fn print_grouped(
+    out: &mut dyn Write,
+    events: &[&Event],
+    date: &str,
+    group_by: GroupBy,
+    theme: &Theme,
+    links: bool,
+    prefix: Option<&str>,
+) -> std::io::Result<()> {
+    let bucket = |event: &Event| -> (bool, i64) {
+        match (group_by, event.year) {
+            (GroupBy::Category, _) => (false, event.category as i64),
+            (GroupBy::Decade, Some(year)) => (false, i64::from(year).div_euclid(10) * 10),
+            (GroupBy::Century, Some(year)) => (false, i64::from(year).div_euclid(100) * 100),
+            // Undated events sort after every dated bucket.
+            (_, None) => (true, 0),
+        }
+    };
+
+    let mut sorted = events.to_vec();
+    // The sort is stable, so each section keeps the selection's order.
+    sorted.sort_by_key(|e| bucket(e));
+
+    print_header(out, date, theme)?;
+    let mut current = None;
+    for event in sorted {
+        let key = bucket(event);
+        if current != Some(key) {
+            current = Some(key);
+            let heading = match (group_by, key) {
+                (GroupBy::Category, _) => capitalize(&event.category.to_string()),
+                (_, (true, _)) => "Undated".to_string(),
+                (_, (false, start)) => format!("{}s", start),
+            };
+            writeln!(out, "\n{}", paint(&theme.header, &format!("== {} ==", heading)))?;
+        }
+        print_event_line(out, event, theme, links, prefix)?;
+    }
+    Ok(())
+}
+
+/// Prints the `--- On This Day: <date> ---` banner.
+fn print_header(out: &mut dyn Write, date: &str, theme: &Theme) -> std::io::Result<()> {
+    let header = format!("--- On This Day: {} ---", date);
+    writeln!(out, "\n{}", paint(&theme.header, &header))
+}
+
+/// Prints the `--attribution` footer, crediting Wikipedia as its license asks
+/// when the text is republished.
+pub fn print_attribution(out: &mut dyn Write, source_url: &str) -> std::io::Result<()> {
+    writeln!(out, "\nSource: Wikipedia, CC BY-SA 4.0 <{}>", source_url)
+}
+
+/// Prints the events as a numbered list to choose from, for `--menu`.
+pub fn print_menu(out: &mut dyn Write, events: &[Event], theme: &Theme) -> std::io::Result<()> {
+    let width = events.len().to_string().len();
+    for (i, event) in events.iter().enumerate() {
+        let year = event.year.map(|y| format!("{}: ", y)).unwrap_or_default();
+        writeln!(out, "{:>width$}. {}{}", i + 1, paint(&theme.year, &year), paint(&theme.text, &event.text))?;
+    }
+    Ok(())
+}
+
+/// Prints one event in full: its text, where it came from, its page's link
+/// and, when the feed included it, the page's opening paragraph.
+pub fn print_detail(out: &mut dyn Write, event: &Event, theme: &Theme, raw_text: bool) -> std::io::Result<()> {
+    let year = event.year.map(|y| format!("Year {}", y)).unwrap_or_else(|| "Undated".to_string());
+    writeln!(out, "\n{}", paint(&theme.header, &format!("--- {} ({}) ---", year, event.category)))?;
+    writeln!(out, "{}", paint(&theme.text, &event.text))?;
+    writeln!(out, "\nFrom the {} Wikipedia feed for {:02}/{:02}", event.language, event.month, event.day)?;
+    if let Some(extract) = event.extract() {
+        writeln!(out, "\n{}", display_text(extract, raw_text))?;
+    }
+    if let Some(url) = event.url() {
+        writeln!(out, "\n{}", paint(&theme.link, url))?;
+    }
+    Ok(())
+}
+
+/// Prints the day's featured picture under its own banner, for `--picture`.
+pub fn print_picture(out: &mut dyn Write, picture: &Picture, theme: &Theme, raw_text: bool) -> std::io::Result<()> {
+    writeln!(out, "\n{}\n", paint(&theme.header, "--- Picture of the Day ---"))?;
+    writeln!(out, "{}", paint(&theme.text, &display_text(&picture.title, raw_text)))?;
+    if let Some(description) = &picture.description {
+        writeln!(out, "{}", paint(&theme.text, &display_text(&description.text, raw_text)))?;
+    }
+    if let Some(url) = &picture.file_page {
+        writeln!(out, "{}", paint(&theme.link, url))?;
+    }
+    Ok(())
+}
+
+/// Prints a single event as `Year N: text`, or just the text if it's undated,
+/// followed by its page URL on the next line when `links` is set. Both lines
+/// start with `prefix`, if given, with its placeholders filled in; on the link
+/// line, an emoji is replaced by blanks to keep the URL lined up.
+fn print_event_line(out: &mut dyn Write, event: &Event, theme: &Theme, links: bool, prefix: Option<&str>) -> std::io::Result<()> {
+    let link_prefix = prefix.map(|p| expand_prefix(&p.replace("{emoji}", "  "), event)).unwrap_or_default();
+    let prefix = prefix.map(|p| expand_prefix(p, event)).unwrap_or_default();
+    let text = paint(&theme.text, &event.text);
+    if let Some(year) = event.year {
+        writeln!(out, "{}{} {}", prefix, paint(&theme.year, &format!("Year {}:", year)), text)?;
+    } else {
+        // For events without a year, like holidays
+        writeln!(out, "{}{}", prefix, text)?;
+    }
+    if let (true, Some(url)) = (links, event.url()) {
+        writeln!(out, "{}  {}", link_prefix, paint(&theme.link, url))?;
+    }
+    Ok(())
+}
+
+/// Fills in the `{date}` (`MM-DD`), `{language}`, `{category}` and `{emoji}`
+/// placeholders of a `--prefix`.
+fn expand_prefix(prefix: &str, event: &Event) -> String {
+    prefix
+        .replace("{date}", &format!("{:02}-{:02}", event.month, event.day))
+        .replace("{language}", &event.language)
+        .replace("{category}", &event.category.to_string())
+        .replace("{emoji}", category_emoji(event.category))
+}
+
+/// The emoji `--emoji` marks events of a category with.
+fn category_emoji(category: Category) -> &'static str {
+    match category {
+        Category::Selected => "⭐",
+        Category::Births => "🎂",
+        Category::Deaths => "🪦",
+        Category::Holidays => "🎉",
+        Category::Events => "📅",
+    }
+}
+
+/// Upper-cases the first letter of `word`.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Prints the selected events as an aligned `Year | Category | Text` table,
+/// wrapping the text column to whatever width the terminal has left.
+/// Only the banner is themed, since escape codes would upset the alignment.
+pub fn print_table(out: &mut dyn Write, events: &[&Event], date: &str, width: usize, theme: &Theme) -> std::io::Result<()> {
+    let years: Vec<String> = events
+        .iter()
+        .map(|e| e.year.map(|y| y.to_string()).unwrap_or_default())
+        .collect();
+    let categories: Vec<String> = events.iter().map(|e| e.category.to_string()).collect();
+
+    let year_width = years.iter().map(|y| y.len()).chain(["Year".len()]).max().unwrap_or(0);
+    let category_width = categories.iter().map(|c| c.len()).chain(["Category".len()]).max().unwrap_or(0);
+    // Two " | " separators sit between the three columns.
+    let text_width = width
+        .saturating_sub(year_width + category_width + 6)
+        .max(MIN_TEXT_WIDTH);
+
+    print_header(out, date, theme)?;
+    writeln!(out)?;
+    writeln!(out, "{:>year_width$} | {:<category_width$} | Text", "Year", "Category")?;
+    writeln!(out, "{}-+-{}-+-{}", "-".repeat(year_width), "-".repeat(category_width), "-".repeat(text_width))?;
+
+    for ((event, year), category) in events.iter().zip(&years).zip(&categories) {
+        let lines = wrap(&event.text, text_width);
+        for (i, line) in lines.iter().enumerate() {
+            let (year, category) = if i == 0 { (year.as_str(), category.as_str()) } else { ("", "") };
+            writeln!(out, "{:>year_width$} | {:<category_width$} | {}", year, category, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints a timeline bar spanning the pool's oldest to newest year, marking
+/// where each selected event falls. Without color (piped output or
+/// `--no-color`) this degrades to a plain text summary of the range.
+pub fn print_timeline(out: &mut dyn Write, pool: &[Event], selected: &[&Event], width: usize, color: bool) -> std::io::Result<()> {
+    let Some(min) = pool.iter().filter_map(|e| e.year).min() else {
+        return Ok(());
+    };
+    let max = pool.iter().filter_map(|e| e.year).max().unwrap_or(min);
+    let picks: Vec<i32> = selected.iter().filter_map(|e| e.year).collect();
+    let picks_label = picks.iter().map(|y| y.to_string()).collect::<Vec<_>>().join(", ");
+
+    if !color || max == min {
+        return writeln!(out, "\nTimeline: {}–{} (picked: {})", min, max, picks_label);
+    }
+
+    let (min_label, max_label) = (min.to_string(), max.to_string());
+    let bar_width = width
+        .saturating_sub(min_label.len() + max_label.len() + 2)
+        .max(MIN_TEXT_WIDTH);
+    let span = f64::from(max - min);
+    let mut bar = vec![false; bar_width];
+    for year in &picks {
+        let position = (f64::from(year - min) / span * (bar_width - 1) as f64).round() as usize;
+        bar[position] = true;
+    }
+    let bar: String = bar
+        .into_iter()
+        .map(|picked| if picked { "\x1b[1;33m█\x1b[0m" } else { "\x1b[2m░\x1b[0m" })
+        .collect();
+
+    writeln!(out, "\n{} {} {}", min_label, bar, max_label)?;
+    writeln!(out, "\x1b[1;33m█\x1b[0m picked: {}", picks_label)
+}
+
+/// Prints a one-line overview of the pool, e.g.
+/// `1215–2019 • 37 events • 12 births • 8 deaths`.
+pub fn print_summary(out: &mut dyn Write, pool: &[Event]) -> std::io::Result<()> {
+    let mut parts = Vec::new();
+    let years = || pool.iter().filter_map(|e| e.year);
+    if let (Some(min), Some(max)) = (years().min(), years().max()) {
+        parts.push(format!("{}–{}", min, max));
+    }
+    parts.push(format!("{} events", pool.len()));
+    for category in [Category::Selected, Category::Births, Category::Deaths, Category::Holidays, Category::Events] {
+        let count = pool.iter().filter(|e| e.category == category).count();
+        if count > 0 {
+            // "37 events • 5 events" would read oddly, so name the catch-all category.
+            let label = if category == Category::Events { "general".to_string() } else { category.to_string() };
+            parts.push(format!("{} {}", count, label));
+        }
+    }
+    writeln!(out, "\n{}", parts.join(" • "))
+}
+
+/// The number of events per category, plus the total, as printed by `--stats`.
+#[derive(Serialize, Default)]
+pub struct CategoryCounts {
+    selected: usize,
+    births: usize,
+    deaths: usize,
+    holidays: usize,
+    events: usize,
+    total: usize,
+}
+
+impl CategoryCounts {
+    /// Counts the given events.
+    pub fn of<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut counts = CategoryCounts::default();
+        for event in events {
+            counts.total += 1;
+            match event.category {
+                Category::Selected => counts.selected += 1,
+                Category::Births => counts.births += 1,
+                Category::Deaths => counts.deaths += 1,
+                Category::Holidays => counts.holidays += 1,
+                Category::Events => counts.events += 1,
+            }
+        }
+        counts
+    }
+
+    /// `(name, count)` pairs in output order, ending with the total.
+    fn rows(&self) -> [(&'static str, usize); 6] {
+        [
+            ("selected", self.selected),
+            ("births", self.births),
+            ("deaths", self.deaths),
+            ("holidays", self.holidays),
+            ("events", self.events),
+            ("total", self.total),
+        ]
+    }
+}
+
+/// Prints the counts as aligned `name: count` lines.
+pub fn print_counts_text(out: &mut dyn Write, counts: &CategoryCounts) -> std::io::Result<()> {
+    for (name, count) in counts.rows() {
+        writeln!(out, "{:<9} {}", format!("{}:", name), count)?;
+    }
+    Ok(())
+}
+
+/// Prints the counts as an HTML list, one `<li>` per category.
+pub fn print_counts_html(out: &mut dyn Write, counts: &CategoryCounts) -> std::io::Result<()> {
+    writeln!(out, "<ul class=\"on-this-day-stats\">")?;
+    for (name, count) in counts.rows() {
+        writeln!(out, "  <li>{}: {}</li>", name, count)?;
+    }
+    writeln!(out, "</ul>")
+}
+
+/// Prints the counts as a single JSON object, pretty-printed or on one line.
+pub fn print_counts_json(out: &mut dyn Write, counts: &CategoryCounts, pretty: bool) -> std::io::Result<()> {
+    let json = if pretty { serde_json::to_string_pretty(counts)? } else { serde_json::to_string(counts)? };
+    writeln!(out, "{}", json)
+}
+
+/// Prints the counts as `category,count` rows under a header.
+pub fn print_counts_delimited(out: &mut dyn Write, counts: &CategoryCounts, delimiter: char, bom: bool) -> std::io::Result<()> {
+    if bom {
+        write!(out, "\u{feff}")?;
+    }
+    writeln!(out, "category{}count", delimiter)?;
+    for (name, count) in counts.rows() {
+        writeln!(out, "{}{}{}", name, delimiter, count)?;
+    }
+    Ok(())
+}
+
+/// Greedily word-wraps `text` into lines of at most `width` characters.
+/// Words longer than `width` are kept whole on their own line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let needed = if current.is_empty() { 0 } else { 1 } + word.chars().count();
+        if !current.is_empty() && current.chars().count() + needed > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// The flat JSON shape of an event: one object per event, with no nesting by
+/// category, so the output loads straight into a table.
+#[derive(Serialize)]
+struct JsonEvent<'a> {
+    /// Only serialized with `--event-id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    /// The date whose feed the event came from, as `MM-DD`.
+    date: String,
+    /// The Wikipedia language edition, e.g. `en`.
+    language: &'a str,
+    category: Category,
+    year: Option<i32>,
+    text: &'a str,
+    /// Only serialized with `--json-include-url`; `Some(None)` becomes `null`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<Option<&'a str>>,
+}
+
+impl<'a> JsonEvent<'a> {
+    fn new(event: &'a Event, include_url: bool, include_id: bool) -> Self {
+        JsonEvent {
+            id: include_id.then(|| event.id()),
+            date: format!("{:02}-{:02}", event.month, event.day),
+            language: &event.language,
+            category: event.category,
+            year: event.year,
+            text: &event.text,
+            url: include_url.then(|| event.url()),
+        }
+    }
+}
+
+/// Prints the events as a single pretty-printed JSON array.
+pub fn print_json(out: &mut dyn Write, events: &[&Event], include_url: bool, include_id: bool) -> std::io::Result<()> {
+    let json: Vec<JsonEvent> = events.iter().map(|e| JsonEvent::new(e, include_url, include_id)).collect();
+    writeln!(out, "{}", serde_json::to_string_pretty(&json)?)
+}
+
+/// Prints the events as JSON Lines, one compact object per line.
+pub fn print_jsonl(out: &mut dyn Write, events: &[&Event], include_url: bool, include_id: bool) -> std::io::Result<()> {
+    for event in events {
+        writeln!(out, "{}", serde_json::to_string(&JsonEvent::new(event, include_url, include_id))?)?;
+    }
+    Ok(())
+}
+
+/// An event in the `--json-by-category` shape, where its category is the key
+/// of the list it's in.
+#[derive(Serialize)]
+struct CategorizedEvent<'a> {
+    year: Option<i32>,
+    text: &'a str,
+    url: Option<&'a str>,
+}
+
+/// The `--json-by-category` document: one list per feed category, mirroring
+/// the API's own layout. Every category is present, even if empty.
+#[derive(Serialize, Default)]
+struct Categorized<'a> {
+    /// Bumped whenever the layout changes incompatibly.
+    schema_version: u32,
+    selected: Vec<CategorizedEvent<'a>>,
+    births: Vec<CategorizedEvent<'a>>,
+    deaths: Vec<CategorizedEvent<'a>>,
+    holidays: Vec<CategorizedEvent<'a>>,
+    events: Vec<CategorizedEvent<'a>>,
+}
+
+/// Prints the events as a pretty-printed JSON object keyed by category, each
+/// list keeping the selection's order.
+pub fn print_json_by_category(out: &mut dyn Write, events: &[&Event]) -> std::io::Result<()> {
+    let mut json = Categorized { schema_version: 1, ..Default::default() };
+    for event in events {
+        let list = match event.category {
+            Category::Selected => &mut json.selected,
+            Category::Births => &mut json.births,
+            Category::Deaths => &mut json.deaths,
+            Category::Holidays => &mut json.holidays,
+            Category::Events => &mut json.events,
+        };
+        list.push(CategorizedEvent { year: event.year, text: &event.text, url: event.url() });
+    }
+    writeln!(out, "{}", serde_json::to_string_pretty(&json)?)
+}
+
+/// What produced a `--with-meta` JSON document.
+pub struct JsonMeta<'a> {
+    pub language: &'a str,
+    /// The full date queried, as `YYYY-MM-DD`.
+    pub date: String,
+    pub event_type: &'a str,
+    /// The feed URL, or the `--from-file` path the events were read from.
+    pub source_url: String,
+}
+
+/// The `--with-meta` JSON document: the request metadata and the events.
+#[derive(Serialize)]
+struct JsonWithMeta<'a> {
+    language: &'a str,
+    date: &'a str,
+    event_type: &'a str,
+    /// When the run fetched the events, in RFC 3339 format.
+    fetched_at: String,
+    source_url: &'a str,
+    events: Vec<JsonEvent<'a>>,
+}
+
+/// Prints the events as a JSON object that also records what was queried.
+pub fn print_json_with_meta(
+    out: &mut dyn Write,
+    meta: &JsonMeta,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    events: &[&Event],
+    include_url: bool,
+    include_id: bool,
+) -> std::io::Result<()> {
+    let document = JsonWithMeta {
+        language: meta.language,
+        date: &meta.date,
+        event_type: meta.event_type,
+        fetched_at: fetched_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        source_url: &meta.source_url,
+        events: events.iter().map(|e| JsonEvent::new(e, include_url, include_id)).collect(),
+    };
+    writeln!(out, "{}", serde_json::to_string_pretty(&document)?)
+}
+
+/// What was asked for, as recorded in an archive.
+#[derive(Serialize)]
+pub struct ArchiveQuery<'a> {
+    /// The full date queried, as `YYYY-MM-DD`.
+    pub date: String,
+    pub language: &'a str,
+    #[serde(rename = "type")]
+    pub event_type: &'a str,
+}
+
+/// An event as recorded in an archive.
+#[derive(Serialize)]
+struct ArchiveEvent<'a> {
+    category: Category,
+    year: Option<i32>,
+    text: &'a str,
+    url: Option<&'a str>,
+}
+
+/// A self-describing snapshot of one day's feed, for `--archive`.
+#[derive(Serialize)]
+struct Archive<'a> {
+    /// Bumped whenever the layout changes incompatibly.
+    archive_version: u32,
+    generator: String,
+    query: &'a ArchiveQuery<'a>,
+    /// When the run fetched the events, in RFC 3339 format.
+    fetched_at: String,
+    events: Vec<ArchiveEvent<'a>>,
+}
+
+/// Prints every fetched event, in feed order, as a single JSON document along
+/// with the query and fetch time, so the data keeps its provenance.
+pub fn print_archive(
+    out: &mut dyn Write,
+    query: &ArchiveQuery,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    events: &[Event],
+) -> std::io::Result<()> {
+    let archive = Archive {
+        archive_version: 1,
+        generator: format!("on-this-day {}", env!("CARGO_PKG_VERSION")),
+        query,
+        fetched_at: fetched_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        events: events
+            .iter()
+            .map(|e| ArchiveEvent { category: e.category, year: e.year, text: &e.text, url: e.url() })
+            .collect(),
+    };
+    writeln!(out, "{}", serde_json::to_string_pretty(&archive)?)
+}
+
+/// Prints the events as an HTML fragment: a `<ul>` of `<li>` entries with the
+/// year in a `<time>` element, and each event's text linked to its page when
+/// `links` is set. With `grouped`, the events are split into one list per
+/// category, each under an `<h3>`. With `full`, the fragment is wrapped in a
+/// complete document in that language, titled after the first event's date.
+pub fn print_html(out: &mut dyn Write, events: &[&Event], links: bool, grouped: bool, full: Option<&str>) -> std::io::Result<()> {
+    if let Some(language) = full {
+        let title = events
+            .first()
+            .map(|e| format!("On This Day: {:02}/{:02}", e.month, e.day))
+            .unwrap_or_else(|| "On This Day".to_string());
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"{}\">", escape_html(language))?;
+        writeln!(out, "<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>", title)?;
+    }
+    let mut sorted = events.to_vec();
+    if grouped {
+        // The sort is stable, so each list keeps the selection's order.
+        sorted.sort_by_key(|e| e.category as i64);
+    }
+
+    writeln!(out, "<section class=\"on-this-day\">")?;
+    let mut current = None;
+    for event in sorted {
+        // Without grouping, everything shares one list.
+        let key = if grouped { event.category } else { Category::default() };
+        if current != Some(key) {
+            if current.is_some() {
+                writeln!(out, "</ul>")?;
+            }
+            if grouped {
+                writeln!(out, "<h3>{}</h3>", capitalize(&event.category.to_string()))?;
+            }
+            writeln!(out, "<ul>")?;
+            current = Some(key);
+        }
+        let text = escape_html(&event.text);
+        let text = match (links, event.url()) {
+            (true, Some(url)) => format!("<a href=\"{}\">{}</a>", escape_html(url), text),
+            _ => text,
+        };
+        match event.year {
+            // `datetime` only takes years of four or more digits, counted from year 1.
+            Some(year) if year > 0 => writeln!(out, "  <li><time datetime=\"{:04}\">{}</time>: {}</li>", year, year, text)?,
+            Some(year) => writeln!(out, "  <li><time>{}</time>: {}</li>", year, text)?,
+            None => writeln!(out, "  <li>{}</li>", text)?,
+        }
+    }
+    if current.is_some() {
+        writeln!(out, "</ul>")?;
+    }
+    writeln!(out, "</section>")?;
+    if full.is_some() {
+        writeln!(out, "</body>\n</html>")?;
+    }
+    Ok(())
+}
+
+/// Escapes the characters that are special in HTML text and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The placeholders a `--template` can use.
+const TEMPLATE_FIELDS: [&str; 7] = ["year", "text", "url", "category", "date", "language", "id"];
+
+/// Checks that every `{...}` in a `--template` is a known placeholder.
+pub fn parse_template(template: &str) -> Result<String, String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("unclosed '{{' in '{}'", template));
+        };
+        let name = &rest[start + 1..start + end];
+        if !TEMPLATE_FIELDS.contains(&name) {
+            return Err(format!("unknown placeholder {{{}}}; the placeholders are {{{}}}", name, TEMPLATE_FIELDS.join("}, {")));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(template.to_string())
+}
+
+/// Prints each event as `template` with its placeholders filled in, one per
+/// line. A placeholder the event has no value for is handled per `missing`.
+pub fn print_template(out: &mut dyn Write, events: &[&Event], template: &str, missing: TemplateMissing) -> std::io::Result<()> {
+    'events: for event in events {
+        let mut line = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}').expect("checked by parse_template");
+            line.push_str(&rest[..start]);
+            let value = match &rest[start + 1..end] {
+                "year" => event.year.map(|y| y.to_string()),
+                "text" => Some(event.text.clone()),
+                "url" => event.url().map(str::to_string),
+                "category" => Some(event.category.to_string()),
+                "date" => Some(format!("{:02}-{:02}", event.month, event.day)),
+                "language" => Some(event.language.clone()),
+                "id" => Some(event.id()),
+                name => unreachable!("unknown placeholder {{{}}} passed parse_template", name),
+            };
+            match (value, missing) {
+                (Some(value), _) => line.push_str(&value),
+                (None, TemplateMissing::Empty) => {}
+                (None, TemplateMissing::Skip) => continue 'events,
+                (None, TemplateMissing::Literal) => line.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        line.push_str(rest);
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Prints each event as its text and its page's URL, separated by a tab, for
+/// `--format link`. Events without a page are left out.
+pub fn print_links(out: &mut dyn Write, events: &[&Event]) -> std::io::Result<()> {
+    for event in events {
+        if let Some(url) = event.url() {
+            writeln!(out, "{}\t{}", event.text.replace(['\t', '\n', '\r'], " "), url)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the events as CSV or TSV, using the same columns as the JSON output.
+/// With `header`, a header row comes first, preceded by a UTF-8 byte-order
+/// mark if `bom` is set.
+pub fn print_delimited(out: &mut dyn Write, events: &[&Event], delimiter: char, bom: bool, header: bool) -> std::io::Result<()> {
+    if header && bom {
+        write!(out, "\u{feff}")?;
+    }
+    let field = |value: &str| -> String {
+        if delimiter == '\t' {
+            // TSV has no quoting, so tabs and line breaks become spaces.
+            value.replace(['\t', '\n', '\r'], " ")
+        } else if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let separator = delimiter.to_string();
+    if header {
+        writeln!(out, "{}", ["date", "language", "category", "year", "text"].join(&separator))?;
+    }
+    for event in events {
+        let row = [
+            format!("{:02}-{:02}", event.month, event.day),
+            field(&event.language),
+            event.category.to_string(),
+            event.year.map(|y| y.to_string()).unwrap_or_default(),
+            field(&event.text),
+        ];
+        writeln!(out, "{}", row.join(&separator))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::config::ThemeConfig;
+use clap::ValueEnum;
+
+/// The built-in color themes selectable with `--theme`.
+#[derive(ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ThemePreset {
+    /// Bright colors for dark terminal backgrounds
+    Dark,
+    /// Deeper colors for light terminal backgrounds
+    Light,
+    /// No colors, only bold and dim
+    Mono,
+}
+
+/// ANSI styles for each element of the text output. An empty style leaves
+/// the element unstyled.
+#[derive(Debug, Default, Clone)]
+pub struct Theme {
+    pub year: String,
+    pub text: String,
+    pub header: String,
+    pub link: String,
+}
+
+impl Theme {
+    /// A theme that applies no styling at all.
+    pub fn plain() -> Theme {
+        Theme::default()
+    }
+
+    /// The styles of a built-in preset.
+    pub fn preset(preset: ThemePreset) -> Theme {
+        let (year, text, header, link) = match preset {
+            ThemePreset::Dark => ("1;93", "97", "1;96", "4;94"),
+            ThemePreset::Light => ("1;33", "30", "1;34", "4;35"),
+            ThemePreset::Mono => ("1", "", "1", "2;4"),
+        };
+        Theme {
+            year: year.to_string(),
+            text: text.to_string(),
+            header: header.to_string(),
+            link: link.to_string(),
+        }
+    }
+
+    /// Builds the theme from the `--theme` flag and the config file's `[theme]`
+    /// table. The flag's preset wins over the config's, and the config's
+    /// per-element colors are applied on top. Returns the theme along with any
+    /// problems found in the config.
+    pub fn resolve(flag: Option<ThemePreset>, config: &ThemeConfig) -> (Theme, Vec<String>) {
+        let mut problems = Vec::new();
+        let config_preset = config.preset.as_deref().and_then(|name| {
+            let preset = ThemePreset::from_str(name, true).ok();
+            if preset.is_none() {
+                problems.push(format!("Unknown theme preset '{}' in config.", name));
+            }
+            preset
+        });
+
+        let mut theme = flag.or(config_preset).map(Theme::preset).unwrap_or_else(Theme::plain);
+        for (style, color) in [
+            (&mut theme.year, &config.year),
+            (&mut theme.text, &config.text),
+            (&mut theme.header, &config.header),
+            (&mut theme.link, &config.link),
+        ] {
+            if let Some(name) = color {
+                match color_code(name) {
+                    Some(code) => *style = code.to_string(),
+                    None => problems.push(format!("Unknown color '{}' in config.", name)),
+                }
+            }
+        }
+        (theme, problems)
+    }
+}
+
+/// Wraps `text` in the given ANSI style, if there is one.
+pub fn paint(style: &str, text: &str) -> String {
+    if style.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    }
+}
+
+/// Maps a standard color name to its ANSI foreground code.
+fn color_code(name: &str) -> Option<&'static str> {
+    let code = match name.to_lowercase().replace('_', "-").as_str() {
+        "default" | "none" => "",
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        "bright-black" | "gray" | "grey" => "90",
+        "bright-red" => "91",
+        "bright-green" => "92",
+        "bright-yellow" => "93",
+        "bright-blue" => "94",
+        "bright-magenta" => "95",
+        "bright-cyan" => "96",
+        "bright-white" => "97",
+        _ => return None,
+    };
+    Some(code)
+}